@@ -0,0 +1,195 @@
+use crate::config::GlobalConfig;
+use crate::git;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow, Context};
+use semver::{Version, VersionReq};
+use sha2::{Sha256, Digest};
+use walkdir::WalkDir;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A single advisory file, e.g. `advisories/fin-http/FINN-2025-0001.toml`:
+///
+/// ```toml
+/// [advisory]
+/// id = "FINN-2025-0001"
+/// package = "fin-http"
+/// title = "Header injection in request builder"
+/// severity = "high"
+/// url = "https://example.com/FINN-2025-0001"
+///
+/// [versions]
+/// affected = [">=0.1.0, <0.3.2"]
+/// patched = [">=0.3.2"]
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+pub struct AdvisoryFile {
+    pub advisory: Advisory,
+    #[serde(default)]
+    pub versions: AdvisoryVersions,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Advisory {
+    pub id: String,
+    pub package: String,
+    pub title: String,
+    pub severity: Severity,
+    pub description: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AdvisoryVersions {
+    /// Ranges known to be vulnerable. Empty means "everything not patched".
+    #[serde(default)]
+    pub affected: Vec<String>,
+    #[serde(default)]
+    pub patched: Vec<String>,
+}
+
+impl AdvisoryFile {
+    /// Returns true if `version` falls in an affected range and in no patched range.
+    pub fn affects(&self, version: &Version) -> Result<bool> {
+        let parse = |ranges: &[String]| -> Result<Vec<VersionReq>> {
+            ranges.iter()
+                .map(|r| VersionReq::parse(r)
+                    .with_context(|| format!("Invalid version range '{}' in advisory {}", r, self.advisory.id)))
+                .collect()
+        };
+
+        let patched = parse(&self.versions.patched)?;
+        if patched.iter().any(|req| req.matches(version)) {
+            return Ok(false);
+        }
+
+        let affected = parse(&self.versions.affected)?;
+        Ok(affected.is_empty() || affected.iter().any(|req| req.matches(version)))
+    }
+}
+
+pub struct AdvisoryDatabase {
+    pub path: PathBuf,
+    pub advisories: Vec<AdvisoryFile>,
+}
+
+impl AdvisoryDatabase {
+    /// Loads every `*.toml` advisory below `root` (the `.git` directory is skipped).
+    pub fn load(root: &Path) -> Result<Self> {
+        if !root.is_dir() {
+            return Err(anyhow!("Advisory database not found at {:?}", root));
+        }
+
+        let mut files: Vec<PathBuf> = WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != ".git")
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "toml"))
+            .map(|e| e.path().to_path_buf())
+            .collect();
+        files.sort();
+
+        let mut advisories = Vec::new();
+        for file in files {
+            let content = fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read advisory {:?}", file))?;
+            let advisory: AdvisoryFile = toml::from_str(&content)
+                .with_context(|| format!("Failed to parse advisory {:?}", file))?;
+            advisories.push(advisory);
+        }
+
+        Ok(AdvisoryDatabase { path: root.to_path_buf(), advisories })
+    }
+
+    /// Opens a database from a local directory or a git URL.
+    /// Git databases are cloned once into `~/.finn/advisory-db` and fast-forwarded on later runs.
+    pub fn open(source: &str, verbose: bool) -> Result<Self> {
+        let local = Path::new(source);
+        if local.is_dir() {
+            return Self::load(local);
+        }
+
+        if !is_remote(source) {
+            return Err(anyhow!("Advisory database not found at '{}'", source));
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(source.as_bytes());
+        let hash = hex::encode(hasher.finalize());
+        let checkout = default_database_dir()?.join(&hash[0..16]);
+
+        let fetch_url = GlobalConfig::load()?.replace_source(source);
+        let fresh = !checkout.exists();
+        if fresh {
+            if verbose { println!("   Fetching advisory database {}...", fetch_url); }
+            fs::create_dir_all(&checkout)?;
+            git::init_mirror(&checkout, &fetch_url)?;
+        } else if verbose {
+            println!("   Updating advisory database {}...", fetch_url);
+        }
+
+        let updated = git::set_origin(&checkout, &fetch_url)
+            .and_then(|_| git::fetch(&checkout, None, true, verbose))
+            .and_then(|_| git::checkout(&checkout, None));
+        if let Err(e) = updated {
+            if fresh {
+                let _ = fs::remove_dir_all(&checkout);
+            }
+            return Err(anyhow::Error::new(e).context(format!("Failed to fetch advisory database from '{}'", source)));
+        }
+
+        Self::load(&checkout)
+    }
+
+    pub fn for_package<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a AdvisoryFile> + 'a {
+        self.advisories.iter().filter(move |a| a.advisory.package == name)
+    }
+}
+
+/// Default location used when no database is configured: `~/.finn/advisory-db`.
+pub fn default_database_dir() -> Result<PathBuf> {
+    Ok(utils::get_home_dir()?.join(".finn").join("advisory-db"))
+}
+
+/// Parses lockfile versions such as `v1.2.0` or `1.2`.
+pub fn parse_version(raw: &str) -> Option<Version> {
+    let trimmed = raw.trim().trim_start_matches('v');
+    if let Ok(v) = Version::parse(trimmed) {
+        return Some(v);
+    }
+    // Accept short forms like "1" or "1.2"
+    let parts: Vec<&str> = trimmed.split('.').collect();
+    if parts.len() < 3 && parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())) {
+        let mut padded = parts.join(".");
+        for _ in parts.len()..3 { padded.push_str(".0"); }
+        return Version::parse(&padded).ok();
+    }
+    None
+}
+
+fn is_remote(source: &str) -> bool {
+    source.starts_with("http") || source.starts_with("git@") || source.starts_with("ssh://") || source.starts_with("git://") || source.starts_with("file://")
+}
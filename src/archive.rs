@@ -0,0 +1,173 @@
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use anyhow::{Result, anyhow, Context};
+use sha2::{Sha256, Digest};
+
+/// Archive formats finn can unpack, recognized by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarGz,
+    TarXz,
+    Zip,
+}
+
+const EXTENSIONS: [(&str, ArchiveFormat); 5] = [
+    (".tar.gz", ArchiveFormat::TarGz),
+    (".tgz", ArchiveFormat::TarGz),
+    (".tar.xz", ArchiveFormat::TarXz),
+    (".txz", ArchiveFormat::TarXz),
+    (".zip", ArchiveFormat::Zip),
+];
+
+pub fn format_of(url: &str) -> Option<ArchiveFormat> {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_lowercase();
+    EXTENSIONS.iter().find(|(ext, _)| path.ends_with(ext)).map(|(_, f)| *f)
+}
+
+pub fn is_archive_url(url: &str) -> bool {
+    (url.starts_with("http") || url.starts_with("file://")) && format_of(url).is_some()
+}
+
+/// Package name and version from an archive file name, e.g. `json-1.2.0.tar.gz` -> ("json", Some("1.2.0")).
+pub fn name_and_version(url: &str) -> (String, Option<String>) {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let lower = file.to_lowercase();
+    let stem = EXTENSIONS.iter()
+        .find(|(ext, _)| lower.ends_with(ext))
+        .map(|(ext, _)| &file[..file.len() - ext.len()])
+        .unwrap_or(file);
+
+    // The version starts at the first "-<digit>" (optionally "-v<digit>")
+    let split = stem.char_indices().find(|&(i, c)| {
+        let rest = &stem[i + 1..];
+        c == '-' && (rest.starts_with(|d: char| d.is_ascii_digit())
+            || (rest.starts_with('v') && rest[1..].starts_with(|d: char| d.is_ascii_digit())))
+    });
+    match split {
+        Some((i, _)) if i > 0 => (stem[..i].to_string(), Some(stem[i + 1..].to_string())),
+        _ => (stem.to_string(), None),
+    }
+}
+
+pub fn download(url: &str) -> Result<Vec<u8>> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return fs::read(path).with_context(|| format!("Failed to read archive '{}'", path));
+    }
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(300))
+        .build()?;
+    let response = client.get(url).send()
+        .with_context(|| format!("Failed to download '{}'", url))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to download '{}': HTTP {}", url, response.status()));
+    }
+    Ok(response.bytes()?.to_vec())
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Unpacks `bytes` into `dest`, refusing entries that would land outside it.
+/// A single top-level directory (as in `lib-1.2/...`) is stripped.
+pub fn extract(bytes: &[u8], format: ArchiveFormat, dest: &Path) -> Result<()> {
+    fs::create_dir_all(dest)?;
+    match format {
+        ArchiveFormat::TarGz => extract_tar(flate2::read::GzDecoder::new(bytes), dest)?,
+        ArchiveFormat::TarXz => extract_tar(xz2::read::XzDecoder::new(bytes), dest)?,
+        ArchiveFormat::Zip => extract_zip(bytes, dest)?,
+    }
+    flatten_single_directory(dest)
+}
+
+fn extract_tar(reader: impl Read, dest: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(false);
+    for entry in archive.entries().context("Failed to read tar archive")? {
+        let mut entry = entry.context("Corrupt tar archive")?;
+        let path = entry.path()?.into_owned();
+        let relative = safe_relative(&path)?;
+
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            let target = entry.link_name()?.ok_or_else(|| anyhow!("Link '{}' has no target", path.display()))?;
+            // Hard link targets are relative to the archive root, symlinks to the link's directory
+            let base = if kind.is_hard_link() { PathBuf::new() } else { relative.parent().map(Path::to_path_buf).unwrap_or_default() };
+            if target.is_absolute() || escapes(&base.join(&target)) {
+                return Err(anyhow!("Refusing to extract '{}': link target '{}' escapes the archive", path.display(), target.display()));
+            }
+        }
+
+        entry.unpack_in(dest).with_context(|| format!("Failed to extract '{}'", path.display()))?;
+    }
+    Ok(())
+}
+
+fn extract_zip(bytes: &[u8], dest: &Path) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).context("Failed to read zip archive")?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let relative = file.enclosed_name()
+            .ok_or_else(|| anyhow!("Refusing to extract '{}': path escapes the archive", file.name()))?;
+        let target = dest.join(safe_relative(&relative)?);
+
+        if file.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = fs::File::create(&target)?;
+        std::io::copy(&mut file, &mut out).with_context(|| format!("Failed to extract '{}'", file.name()))?;
+    }
+    Ok(())
+}
+
+/// A relative path with no `..`, root or drive components.
+fn safe_relative(path: &Path) -> Result<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            _ => return Err(anyhow!("Refusing to extract '{}': path escapes the archive", path.display())),
+        }
+    }
+    Ok(clean)
+}
+
+/// Whether a relative path climbs above its starting point.
+fn escapes(path: &Path) -> bool {
+    let mut depth: i32 = 0;
+    for component in path.components() {
+        match component {
+            Component::ParentDir => depth -= 1,
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            _ => return true,
+        }
+        if depth < 0 {
+            return true;
+        }
+    }
+    false
+}
+
+fn flatten_single_directory(dest: &Path) -> Result<()> {
+    let entries: Vec<_> = fs::read_dir(dest)?.filter_map(|e| e.ok()).collect();
+    if entries.len() != 1 || !entries[0].file_type()?.is_dir() {
+        return Ok(());
+    }
+
+    let inner = entries[0].path();
+    let staging = dest.with_extension("flatten");
+    fs::rename(&inner, &staging)?;
+    fs::remove_dir(dest)?;
+    fs::rename(&staging, dest)?;
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use crate::archive::{self, ArchiveFormat};
+use crate::cache::{self, CacheEntry, CacheIndex};
+use crate::filelock;
+use crate::integrity;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+use anyhow::{Result, anyhow, Context};
+
+const MANIFEST: &str = "manifest.json";
+const FORMAT: u32 = 1;
+
+/// `manifest.json` at the root of a bundle. Each entry's files are stored under `cache/<key>/`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleManifest {
+    pub format: u32,
+    pub finn_version: String,
+    pub entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub imported: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+/// Packs the cache entries `keys` (directory names under `~/.finn/cache/registry`) into a gzipped tarball.
+pub fn create(path: &Path, keys: &[String]) -> Result<()> {
+    let index = CacheIndex::load()?;
+    let cache_root = cache::get_cache_dir()?;
+
+    let mut entries = BTreeMap::new();
+    for key in keys {
+        let entry = index.entries.get(key)
+            .ok_or_else(|| anyhow!("Cache entry '{}' is not in the cache index. Run 'finn fetch' and try again.", key))?;
+        entries.insert(key.clone(), entry.clone());
+    }
+    let manifest = BundleManifest { format: FORMAT, finn_version: env!("CARGO_PKG_VERSION").to_string(), entries };
+
+    let write = || -> Result<()> {
+        let file = fs::File::create(path)?;
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(file, flate2::Compression::default()));
+        builder.follow_symlinks(false);
+
+        let json = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(json.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(utils::unix_now());
+        header.set_cksum();
+        builder.append_data(&mut header, MANIFEST, json.as_slice())?;
+
+        for key in manifest.entries.keys() {
+            builder.append_dir_all(format!("cache/{}", key), cache_root.join(key))
+                .with_context(|| format!("Failed to add cache entry '{}'", key))?;
+        }
+        builder.into_inner()?.finish()?;
+        Ok(())
+    };
+
+    write().inspect_err(|_| { let _ = fs::remove_file(path); })
+        .with_context(|| format!("Failed to write bundle {:?}", path))
+}
+
+/// Unpacks a bundle into the cache. Entries that are already cached are left alone unless `force`.
+pub fn import(path: &Path, force: bool) -> Result<ImportSummary> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read bundle {:?}", path))?;
+    let cache_root = cache::get_cache_dir()?;
+    let staging = cache_root.with_file_name(format!(".import-{}", std::process::id()));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+
+    let result = archive::extract(&bytes, ArchiveFormat::TarGz, &staging)
+        .with_context(|| format!("{:?} is not a valid finn bundle", path))
+        .and_then(|_| import_staged(&staging, &cache_root, force));
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+fn import_staged(staging: &Path, cache_root: &Path, force: bool) -> Result<ImportSummary> {
+    let manifest: BundleManifest = serde_json::from_str(
+        &fs::read_to_string(staging.join(MANIFEST)).context("Bundle has no manifest.json")?
+    ).context("Failed to parse the bundle manifest")?;
+    if manifest.format != FORMAT {
+        return Err(anyhow!(
+            "Unsupported bundle format {} (created by finn {}); this finn reads format {}.",
+            manifest.format, manifest.finn_version, FORMAT
+        ));
+    }
+
+    let mut summary = ImportSummary::default();
+    for (key, entry) in manifest.entries {
+        let mut components = Path::new(&key).components();
+        if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+            return Err(anyhow!("Refusing to import invalid cache entry name '{}'", key));
+        }
+
+        let source = staging.join("cache").join(&key);
+        if !source.is_dir() {
+            return Err(anyhow!("Bundle is missing the files for '{}'", key));
+        }
+        if !integrity::matches(&source, &entry.checksum)? {
+            return Err(anyhow!("Bundle entry '{}' does not match its recorded checksum; the bundle is corrupted.", key));
+        }
+
+        let _entry_lock = filelock::cache_entry(&key, None)?;
+        let dest = cache_root.join(&key);
+        if dest.exists() {
+            if !force {
+                summary.skipped.push(key);
+                continue;
+            }
+            fs::remove_dir_all(&dest).context("Failed to replace existing cache entry")?;
+        }
+        fs::rename(&source, &dest).with_context(|| format!("Failed to move '{}' into the cache", key))?;
+
+        let _index_lock = filelock::cache_index(None)?;
+        let mut index = CacheIndex::load()?;
+        index.entries.insert(key.clone(), CacheEntry {
+            size: cache::dir_size(&dest),
+            last_used: utils::unix_now(),
+            ..entry
+        });
+        index.save()?;
+        summary.imported.push(key);
+    }
+    Ok(summary)
+}
@@ -1,9 +1,70 @@
+use crate::archive;
+use crate::config::GlobalConfig;
+use crate::filelock::{self, FileLock};
+use crate::git;
+use crate::integrity;
+use crate::policy::Policy;
 use crate::utils;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::fs;
-use std::process::Command;
 use anyhow::{Result, anyhow, Context};
+use indicatif::MultiProgress;
 use sha2::{Sha256, Digest};
+use walkdir::WalkDir;
+
+/// Metadata for one directory under `~/.finn/cache/registry`, keyed by directory name.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheEntry {
+    pub name: String,
+    pub source: String,
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    pub commit: Option<String>,
+    pub size: u64,
+    /// Unix timestamp of the last install that used this entry
+    pub last_used: u64,
+    /// Package hash at the time the entry was last used, for `finn cache verify`
+    pub checksum: String,
+    /// Only the requested commit was fetched, not the full history
+    #[serde(default)]
+    pub shallow: bool,
+    /// SHA-256 of the downloaded file, for archive sources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_sha256: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CacheIndex {
+    pub entries: BTreeMap<String, CacheEntry>,
+}
+
+impl CacheIndex {
+    fn path() -> Result<PathBuf> {
+        Ok(utils::get_home_dir()?.join(".finn").join("cache").join("index.json"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(CacheIndex::default());
+        }
+        let content = fs::read_to_string(&path).context("Failed to read cache index")?;
+        // A corrupt index only loses metadata; the cached sources are still usable
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&path, content).context("Failed to write cache index")?;
+        Ok(())
+    }
+}
 
 pub fn get_cache_dir() -> Result<PathBuf> {
     let home = utils::get_home_dir()?;
@@ -14,70 +75,306 @@ pub fn get_cache_dir() -> Result<PathBuf> {
     Ok(cache)
 }
 
-pub fn ensure_cached(name: &str, url: &str, version: Option<&str>, verbose: bool) -> Result<PathBuf> {
-    let cache_root = get_cache_dir()?;
-    
-    // Hash URL + Version to create unique cache key
+/// Directory name for a source: `<name>-<first 8 hex chars of sha256(url + version)>`.
+pub fn cache_key(name: &str, url: &str, version: Option<&str>) -> String {
     let mut hasher = Sha256::new();
     hasher.update(url.as_bytes());
     if let Some(v) = version {
         hasher.update(v.as_bytes());
     }
     let hash = hex::encode(hasher.finalize());
-    
-    let cache_path = cache_root.join(format!("{}-{}", name, &hash[0..8]));
+    format!("{}-{}", name, &hash[0..8])
+}
+
+/// How aggressively cached git sources are updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchMode {
+    /// Fetch when the requested ref is a branch or missing; trust immutable commits and tags.
+    Auto,
+    /// Always fetch (`--refresh`).
+    Refresh,
+    /// Never touch the network (`--offline`).
+    Offline,
+}
+
+impl FetchMode {
+    pub fn from_flags(refresh: bool, offline: bool) -> Self {
+        if offline {
+            FetchMode::Offline
+        } else if refresh {
+            FetchMode::Refresh
+        } else {
+            FetchMode::Auto
+        }
+    }
+}
+
+/// A source to materialize in the cache.
+pub struct CacheRequest<'a> {
+    pub name: &'a str,
+    pub url: &'a str,
+    /// Tag, branch or commit as written by the user (part of the cache key)
+    pub version: Option<&'a str>,
+    /// Commit recorded in finn.lock; checked out instead of the tip of `version`
+    pub pinned: Option<&'a str>,
+    /// Initialize git submodules (recursively) after checkout
+    pub submodules: bool,
+    /// Expected SHA-256 of an archive download
+    pub sha256: Option<&'a str>,
+    /// Checked against the URL of every submodule before it is fetched
+    pub policy: &'a Policy,
+}
+
+/// A materialized cache entry. The entry stays locked until this is dropped, so another
+/// install sharing the entry (e.g. a sibling monorepo package with other submodule settings)
+/// cannot check out a different tree while this one is copied and hashed.
+pub struct CachedSource {
+    pub path: PathBuf,
+    /// SHA-256 of the downloaded file, for archive sources
+    pub archive_sha256: Option<String>,
+    _lock: FileLock,
+}
+
+/// `progress` is the display that messages about waiting for other finn processes are shown around.
+pub fn ensure_cached(request: &CacheRequest, mode: FetchMode, verbose: bool, progress: Option<&MultiProgress>) -> Result<CachedSource> {
+    let key = cache_key(request.name, request.url, request.version);
+    let cache_path = get_cache_dir()?.join(&key);
+
+    // Handed to the caller, so concurrent installs never see a half-written or changing entry
+    let entry_lock = filelock::cache_entry(&key, progress)?;
+    // Entries stay keyed and recorded by the original URL; only the download goes to the mirror
+    let config = GlobalConfig::load()?;
+    let fetch_url = config.replace_source(request.url);
+    if verbose && fetch_url != request.url {
+        println!("   Using mirror {} for {}", fetch_url, request.url);
+    }
+    let archive_sha256 = if archive::is_archive_url(request.url) {
+        Some(populate_archive(&cache_path, &key, request, &fetch_url, mode, verbose)?)
+    } else {
+        populate(&cache_path, request, &config, &fetch_url, mode, verbose)?;
+        None
+    };
+
+    record_use(&key, request, &cache_path, archive_sha256.clone(), progress)?;
+    enforce_size_limit(&key, verbose, progress)?;
+
+    Ok(CachedSource { path: cache_path, archive_sha256, _lock: entry_lock })
+}
+
+/// Downloads, verifies and unpacks an archive. Archives are immutable once their hash is known,
+/// so an existing entry is reused unless refreshing or its hash does not match `request.sha256`.
+fn populate_archive(cache_path: &Path, key: &str, request: &CacheRequest, url: &str, mode: FetchMode, verbose: bool) -> Result<String> {
+    let matches = |digest: &str| request.sha256.is_none_or(|e| e.eq_ignore_ascii_case(digest));
+
+    let known = CacheIndex::load()?.entries.get(key).and_then(|e| e.archive_sha256.clone());
+    if let Some(known) = known.filter(|_| cache_path.exists())
+        && (mode == FetchMode::Offline || (mode == FetchMode::Auto && matches(&known)))
+    {
+        if !matches(&known) {
+            return Err(checksum_mismatch(url, request.sha256.unwrap_or_default(), &known));
+        }
+        if verbose { println!("   Using cached archive from {:?}", cache_path); }
+        return Ok(known);
+    }
+
+    if mode == FetchMode::Offline {
+        return Err(anyhow!("'{}' is not in the cache and --offline was given.", url));
+    }
+    if verbose { println!("   Downloading archive {}...", url); }
+    let bytes = archive::download(url)?;
+    let digest = archive::sha256_hex(&bytes);
+    if !matches(&digest) {
+        return Err(checksum_mismatch(url, request.sha256.unwrap_or_default(), &digest));
+    }
+
+    let format = archive::format_of(url).ok_or_else(|| anyhow!("Unsupported archive format: '{}'", url))?;
+    let staging = cache_path.with_extension("partial");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    if let Err(e) = archive::extract(&bytes, format, &staging) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e.context(format!("Failed to unpack '{}'", url)));
+    }
+    if cache_path.exists() {
+        fs::remove_dir_all(cache_path).context("Failed to clear old cache")?;
+    }
+    fs::rename(&staging, cache_path).context("Failed to move archive into the cache")?;
+    Ok(digest)
+}
+
+fn checksum_mismatch(url: &str, expected: &str, actual: &str) -> anyhow::Error {
+    anyhow!(
+        "Checksum mismatch for '{}'!\nExpected: {}\nActual:   {}\nThe archive differs from the one that was pinned.",
+        url, expected, actual
+    )
+}
+
+fn populate(cache_path: &Path, request: &CacheRequest, config: &GlobalConfig, url: &str, mode: FetchMode, verbose: bool) -> Result<()> {
 
     // Local Path Logic (Copy)
     let source_path = std::path::Path::new(url);
     if source_path.exists() && source_path.is_dir() {
         if verbose { println!("   Detected local source: {:?}", source_path); }
         if cache_path.exists() {
-            fs::remove_dir_all(&cache_path).context("Failed to clear old cache")?;
+            fs::remove_dir_all(cache_path).context("Failed to clear old cache")?;
         }
-        fs::create_dir_all(&cache_path)?;
-        let options = fs_extra::dir::CopyOptions::new().content_only(true).overwrite(true);
-        if let Err(e) = fs_extra::dir::copy(source_path, &cache_path, &options) {
+        fs::create_dir_all(cache_path)?;
+        if let Err(e) = utils::copy_dir(source_path, cache_path) {
             return Err(anyhow!("Failed to copy local package: {}", e));
         }
-        return Ok(cache_path);
+        return Ok(());
     }
-
-    // Remote Git Logic
-    if cache_path.exists() {
-        if verbose { println!("   Using cached version from {:?}", cache_path); }
-        return Ok(cache_path);
+    let is_local_path = !url.contains("://") && !url.starts_with("git@");
+    if is_local_path && cache_path.exists() && !cache_path.join(".git").exists() {
+        // A copy of a local folder that is no longer there, e.g. on a machine seeded by `finn bundle import`
+        if mode == FetchMode::Offline {
+            if verbose { println!("   Using cached copy of {}", url); }
+            return Ok(());
+        }
+        return Err(anyhow!("Local package path '{}' does not exist. Use --offline to install the cached copy.", url));
     }
 
-    if verbose { println!("   Downloading to cache..."); }
+    // Remote Git Logic: the cache entry is a mirror that is fetched as needed
+    let target = request.pinned.or(request.version);
+    let fresh_clone = !cache_path.exists();
 
-    // Clone
-    let status = Command::new("git")
-        .arg("clone")
-        .arg(url) // Don't use --depth=1 if we need to checkout specific tags later, unless we fetch specific tag
-        .arg(&cache_path)
-        .status()
-        .context("Failed to clone to cache")?;
+    if fresh_clone {
+        if mode == FetchMode::Offline {
+            return Err(anyhow!("'{}' is not in the cache and --offline was given.", url));
+        }
+        if verbose { println!("   Downloading to cache..."); }
+        fs::create_dir_all(cache_path)?;
+        let fetched = git::init_mirror(cache_path, url)
+            .and_then(|_| git::fetch(cache_path, target, true, verbose));
+        if let Err(e) = fetched {
+            let _ = fs::remove_dir_all(cache_path);
+            return Err(anyhow::Error::new(e).context("Failed to clone to cache"));
+        }
+    } else {
+        let needs_fetch = match mode {
+            FetchMode::Offline => false,
+            FetchMode::Refresh => true,
+            FetchMode::Auto => !target.is_some_and(|r| git::is_immutable_ref_present(cache_path, r)),
+        };
 
-    if !status.success() {
-        return Err(anyhow!("Git clone failed"));
+        if needs_fetch {
+            if verbose { println!("   Fetching updates for {}...", url); }
+            git::set_origin(cache_path, url)
+                .and_then(|_| git::fetch(cache_path, target, git::is_shallow(cache_path), verbose))
+                .with_context(|| format!("Failed to fetch updates for '{}'", url))?;
+        } else if verbose {
+            println!("   Using cached version from {:?}", cache_path);
+        }
     }
 
-    // Checkout Version (if specified)
-    if let Some(ver) = version {
-        if verbose { println!("   Checking out version '{}'...", ver); }
-        let checkout_status = Command::new("git")
-            .arg("checkout")
-            .arg(ver)
-            .current_dir(&cache_path)
-            .status()
-            .context("Failed to checkout version")?;
+    if let (true, Some(r)) = (verbose, target) {
+        println!("   Checking out '{}'...", r);
+    }
 
-        if !checkout_status.success() {
+    if let Err(e) = git::checkout(cache_path, target) {
+        if fresh_clone {
             // Cleanup failed cache
-            let _ = fs::remove_dir_all(&cache_path);
-            return Err(anyhow!("Failed to checkout version '{}'. Does it exist?", ver));
+            let _ = fs::remove_dir_all(cache_path);
         }
+        let shown = target.unwrap_or("HEAD");
+        let hint = if mode == FetchMode::Offline { " It may not be cached yet; retry without --offline." } else { " Does it exist?" };
+        return Err(anyhow::Error::new(e).context(format!("Failed to checkout version '{}'.{}", shown, hint)));
+    }
+
+    if request.submodules {
+        if verbose { println!("   Updating submodules..."); }
+        // Submodules are sources of their own: they go through the policy and the mirrors too
+        let resolve_url = |path: &str, sub_url: &str| -> Result<String> {
+            request.policy.check_source(&format!("{} (submodule {})", request.name, path), sub_url)?;
+            let fetch_url = config.replace_source(sub_url);
+            if verbose && fetch_url != sub_url {
+                println!("   Using mirror {} for {}", fetch_url, sub_url);
+            }
+            Ok(fetch_url)
+        };
+        git::update_submodules(cache_path, mode == FetchMode::Offline, &resolve_url).context("Failed to update git submodules")?;
+    } else if cache_path.join(".gitmodules").exists() {
+        // The entry may be shared with a spec that enabled submodules; don't leak their files
+        git::deinit_submodules(cache_path)?;
     }
 
-    Ok(cache_path)
+    Ok(())
+}
+
+/// Total size in bytes of all files below `path`.
+pub fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// The entry is described before taking the index lock, which parallel installs all contend for.
+fn record_use(key: &str, request: &CacheRequest, path: &Path, archive_sha256: Option<String>, progress: Option<&MultiProgress>) -> Result<()> {
+    let entry = CacheEntry {
+        name: request.name.to_string(),
+        source: request.url.to_string(),
+        reference: request.version.map(|v| v.to_string()),
+        commit: git::head_commit(path),
+        size: dir_size(path),
+        last_used: utils::unix_now(),
+        checksum: integrity::calculate_package_hash(path)?,
+        shallow: git::is_shallow(path),
+        archive_sha256,
+    };
+
+    let _index_lock = filelock::cache_index(progress)?;
+    let mut index = CacheIndex::load()?;
+    index.entries.insert(key.to_string(), entry);
+    index.save()
+}
+
+/// Evicts least recently used entries until the cache fits `[cache] max-size`.
+/// The entry identified by `keep` (the one just used) is never evicted.
+fn enforce_size_limit(keep: &str, verbose: bool, progress: Option<&MultiProgress>) -> Result<()> {
+    let max_size = match GlobalConfig::load()?.cache.max_size {
+        Some(s) => utils::parse_size(&s)?,
+        None => return Ok(()),
+    };
+
+    let _index_lock = filelock::cache_index(progress)?;
+    let mut index = CacheIndex::load()?;
+    let mut total: u64 = index.entries.values().map(|e| e.size).sum();
+    if total <= max_size {
+        return Ok(());
+    }
+
+    let mut candidates: Vec<(String, u64, u64)> = index.entries.iter()
+        .filter(|(k, _)| k.as_str() != keep)
+        .map(|(k, e)| (k.clone(), e.last_used, e.size))
+        .collect();
+    candidates.sort_by_key(|(_, last_used, _)| *last_used);
+
+    let cache_root = get_cache_dir()?;
+    for (key, _, size) in candidates {
+        if total <= max_size {
+            break;
+        }
+        // Entries in use by another process are skipped rather than waited for
+        let Some(_entry_lock) = filelock::try_cache_entry(&key)? else { continue };
+        if verbose { println!("   Evicting {} from cache (size limit)", key); }
+        remove_entry(&cache_root, &key)?;
+        index.entries.remove(&key);
+        total = total.saturating_sub(size);
+    }
+
+    index.save()
+}
+
+/// Deletes a cache directory; missing directories are not an error.
+pub fn remove_entry(cache_root: &Path, key: &str) -> Result<()> {
+    let path = cache_root.join(key);
+    if path.exists() {
+        fs::remove_dir_all(&path).with_context(|| format!("Failed to remove cache entry {:?}", path))?;
+    }
+    Ok(())
 }
@@ -1,35 +1,85 @@
-use crate::config::FinnConfig;
-use crate::lock::FinnLock;
-use crate::validator::validate_package;
+use crate::config::{DependencySpec, DetailedDependency, FinnConfig};
+use crate::lock::{FinnLock, LockedPackage};
+use crate::validator::{validate_package, validate_package_name};
 use crate::FinnContext;
+use crate::archive;
 use crate::cache;
+use crate::store;
+use crate::filelock;
+use crate::git;
 use crate::utils;
-use crate::integrity;
+use crate::integrity::{self, FileManifest, HashFormat};
+use crate::license;
 use crate::registry::RegistryClient;
-use std::path::Path;
+use crate::policy::Policy;
+use crate::typosquat;
+use std::io::IsTerminal;
+use std::path::{Component, Path, PathBuf};
 use std::fs;
-use std::process::Command;
 use std::collections::HashSet;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
 use anyhow::{Result, anyhow, Context};
 use colored::*;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use indicatif::MultiProgress;
 
 pub struct PackageSource {
     pub name: String,
     pub url: String,
     pub version: Option<String>,
     pub is_official: bool,
+    /// Package directory inside the repository
+    pub subdir: Option<String>,
+    pub submodules: bool,
+    /// Expected SHA-256, for archive sources
+    pub sha256: Option<String>,
 }
 
-pub fn run(package_ref: &str, ctx: &FinnContext) -> Result<()> {
+/// Shared, read-only state for a recursive installation.
+pub struct InstallEnv<'a> {
+    pub packages_dir: &'a Path,
+    pub client: &'a RegistryClient,
+    pub policy: &'a Policy,
+    pub ctx: &'a FinnContext,
+    /// Check out the commits recorded in finn.lock instead of re-resolving refs (used by sync)
+    pub honor_lock: bool,
+    /// Per-package progress of concurrent installs
+    pub multi: &'a MultiProgress,
+    /// Format of the checksums recorded in finn.lock
+    pub checksum_format: HashFormat,
+}
+
+impl InstallEnv<'_> {
+    /// The project's `.finn` directory
+    pub fn env_path(&self) -> &Path {
+        self.packages_dir.parent().unwrap_or(Path::new("."))
+    }
+}
+
+pub fn run(package_ref: &str, subdir: Option<String>, submodules: bool, allow_confusable: bool, ctx: &FinnContext) -> Result<()> {
+    let _env_lock = filelock::project_env()?;
     let mut config = FinnConfig::load()?;
     let mut lock = FinnLock::load()?;
+    let policy = Policy::load()?;
 
     // Initialize Registry Client (Check config first)
     let registry_url = config.registry.as_ref().map(|r| r.url.clone());
     let client = RegistryClient::new(registry_url);
     
-    // Resolve package source
-    let source = resolve_source(package_ref, &client)?;
+    // Resolve package source; a monorepo subfolder is named after its last path component
+    let mut source = resolve_source(package_ref, &client)?;
+    if let Some(dir) = &subdir {
+        source.name = subdir_name(dir)?;
+        source.subdir = Some(dir.clone());
+    }
+    source.submodules = submodules;
+    validate_package_name(&source.name)?;
+
+    if source.is_official && !allow_confusable {
+        check_confusable(&source.name, &client, ctx)?;
+    }
 
     if !ctx.quiet { 
         let v_str = source.version.as_deref().unwrap_or("latest");
@@ -41,137 +91,477 @@ pub fn run(package_ref: &str, ctx: &FinnContext) -> Result<()> {
     
     // Store with version if present: "url#version" or just "url"
     // For local paths, we just store the path.
-    let config_value = if let Some(v) = &source.version {
-        // If it's a registry/git url, append #version for storage? 
-        // Or just store the raw input? Storing raw input "user/repo@v1" preserves intent.
-        package_ref.to_string()
+    // Storing the raw input ("user/repo@v1") preserves intent.
+    let is_archive = archive::is_archive_url(&source.url);
+    let config_value = if is_archive {
+        // Completed with the computed sha256 once downloaded
+        DependencySpec::Detailed(DetailedDependency {
+            url: Some(source.url.clone()),
+            subdir,
+            ..Default::default()
+        })
+    } else if subdir.is_some() || submodules {
+        let (base, version) = split_version(package_ref);
+        DependencySpec::Detailed(DetailedDependency {
+            git: Some(base.to_string()),
+            version,
+            subdir,
+            submodules,
+            ..Default::default()
+        })
     } else {
-        package_ref.to_string()
+        DependencySpec::Simple(package_ref.to_string())
     };
 
-    config.packages.as_mut().unwrap().insert(source.name.clone(), config_value);
-    config.save()?;
-
     // Begin recursive installation
-    let mut visited = HashSet::new();
     let env_path = Path::new(&config.project.envpath);
     let packages_dir = env_path.join("packages");
     
     if !packages_dir.exists() { fs::create_dir_all(&packages_dir)?; }
 
-    install_recursive(
-        &source.name, 
-        &source.url, 
-        source.version.as_deref(),
-        &packages_dir, 
-        &mut lock, 
-        &mut visited,
-        &client,
-        ctx
-    )?;
+    let multi = MultiProgress::new();
+    let env = InstallEnv { packages_dir: &packages_dir, client: &client, policy: &policy, ctx, honor_lock: false, multi: &multi, checksum_format: config.checksum_format() };
+    let name = source.name.clone();
+    install_packages(vec![(name.clone(), source)], &env, &mut lock)?;
+
+    let mut config_value = config_value;
+    if let DependencySpec::Detailed(detail) = &mut config_value
+        && is_archive
+    {
+        detail.sha256 = lock.packages.get(&name).and_then(|p| p.sha256.clone());
+    }
+    config.packages.as_mut().unwrap().insert(name.clone(), config_value);
 
+    // Only record the package once it (and its dependencies) passed validation and policy
+    config.save()?;
     lock.save()?;
 
-    if !ctx.quiet { println!("{} Package '{}' installed.", "[OK]".green(), source.name); }
+    if !ctx.quiet { println!("{} Package '{}' installed.", "[OK]".green(), name); }
+    Ok(())
+}
+
+/// Warns (or fails when not interactive) if a registry name looks like a typo of a more popular package.
+fn check_confusable(name: &str, client: &RegistryClient, ctx: &FinnContext) -> Result<()> {
+    let popular = match client.get_popular_packages() {
+        Ok(p) => p,
+        Err(e) => {
+            if ctx.verbose { println!("   Skipping name similarity check: {}", e); }
+            return Ok(());
+        }
+    };
+
+    let similar = match typosquat::find_confusable(name, &popular) {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    let warning = format!(
+        "Package '{}' looks like a confusable of the more popular '{}' ({} downloads): the name {}.",
+        name, similar.name, similar.downloads, similar.reason
+    );
+
+    if ctx.quiet || !std::io::stdin().is_terminal() {
+        return Err(anyhow!("{}\nIf this is the package you want, re-run with --allow-confusable.", warning));
+    }
+
+    println!("{} {}", "[WARN]".yellow(), warning);
+    let proceed = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Install '{}' anyway?", name))
+        .default(false)
+        .interact()?;
+
+    if !proceed {
+        return Err(anyhow!("Installation of '{}' cancelled.", name));
+    }
+    Ok(())
+}
+
+/// A package to install, found while walking the dependency graph.
+struct InstallJob {
+    name: String,
+    source: PackageSource,
+    /// 1 for packages listed in the root finn.toml
+    depth: usize,
+}
+
+/// What installing one package produced: its lock entry and its resolved dependencies.
+struct Installed {
+    locked: LockedPackage,
+    dependencies: Vec<(String, PackageSource)>,
+}
+
+/// Installs `roots` and all of their dependencies, up to `ctx.jobs` packages at a time.
+/// The graph is processed breadth-first and each level is merged in name order, so which
+/// source wins for a shared dependency and what ends up in the lockfile never depend on
+/// which download finishes first.
+pub fn install_packages(roots: Vec<(String, PackageSource)>, env: &InstallEnv, lock: &mut FinnLock) -> Result<()> {
+    let mut visited = HashSet::new();
+    let mut level: Vec<InstallJob> = Vec::new();
+    for (name, source) in roots {
+        if visited.insert(name.clone()) {
+            level.push(InstallJob { name, source, depth: 1 });
+        }
+    }
+    level.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // Workers compare against the lockfile as it was before this run
+    let snapshot = lock.clone();
+    while !level.is_empty() {
+        let mut next = Vec::new();
+        for (job, result) in level.iter().zip(install_level(&level, env, &snapshot)) {
+            // Only jobs after a failed one are ever skipped, so the first error is reached first
+            let Some(result) = result else { continue };
+            let installed = result?;
+            lock.update(job.name.clone(), installed.locked);
+
+            let mut dependencies = installed.dependencies;
+            dependencies.sort_by(|a, b| a.0.cmp(&b.0));
+            for (name, source) in dependencies {
+                if visited.insert(name.clone()) {
+                    next.push(InstallJob { name, source, depth: job.depth + 1 });
+                }
+            }
+        }
+        level = next;
+    }
     Ok(())
 }
 
-pub fn install_recursive(
-    name: &str, 
-    url: &str, 
-    version: Option<&str>,
-    packages_dir: &Path, 
-    lock: &mut FinnLock,
-    visited: &mut HashSet<String>,
-    client: &RegistryClient,
-    ctx: &FinnContext
-) -> Result<()> {
-    if visited.contains(name) { return Ok(()); }
-    visited.insert(name.to_string());
+/// Runs one level of the graph on a bounded pool of worker threads. Results are in job order;
+/// after the first failure no new jobs are started and their slots stay `None`.
+fn install_level(jobs: &[InstallJob], env: &InstallEnv, snapshot: &FinnLock) -> Vec<Option<Result<Installed>>> {
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let slots: Vec<Mutex<Option<Result<Installed>>>> = jobs.iter().map(|_| Mutex::new(None)).collect();
+    let workers = env.ctx.jobs.clamp(1, jobs.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while !failed.load(Ordering::SeqCst) {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(job) = jobs.get(i) else { break };
+                    let result = install_one(job, env, snapshot);
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    *slots[i].lock().unwrap() = Some(result);
+                }
+            });
+        }
+    });
 
-    let pb = utils::create_spinner(&format!("Installing {}...", name), ctx.quiet);
+    slots.into_iter().map(|slot| slot.into_inner().unwrap()).collect()
+}
+
+/// Fetches, validates and links a single package, and resolves its dependencies.
+fn install_one(job: &InstallJob, env: &InstallEnv, lock: &FinnLock) -> Result<Installed> {
+    let name = job.name.as_str();
+    let source = &job.source;
+    let ctx = env.ctx;
+    let url = source.url.as_str();
+    let version = source.version.as_deref();
+    validate_package_name(name)?;
+    env.policy.check_package(name)?;
+    env.policy.check_source(name, url)?;
+    env.policy.check_depth(name, job.depth)?;
+    if env.honor_lock {
+        env.policy.check_checksum(name, lock.packages.get(name).map(|p| p.checksum.as_str()))?;
+    }
+
+    let pb = env.multi.add(utils::create_spinner(&format!("Installing {}...", name), ctx.quiet));
+
+    // Pin to the locked commit (or archive hash) if the lock entry still describes the same source
+    let version_str = version.unwrap_or("HEAD").to_string();
+    let locked = lock.packages.get(name).filter(|p| env.honor_lock && describes(p, source));
+    let pinned = locked.map(|p| p.commit.clone()).filter(|c| c != "unknown");
+    let expected_sha256 = source.sha256.clone().or_else(|| locked.and_then(|p| p.sha256.clone()));
 
     // Download to Cache
-    let cached_path = match cache::ensure_cached(name, url, version, ctx.verbose) {
-        Ok(p) => p,
+    let cache_name = cache_name(name, url, source.subdir.as_deref());
+    let request = cache::CacheRequest {
+        name: &cache_name,
+        url,
+        version,
+        pinned: pinned.as_deref(),
+        submodules: source.submodules,
+        sha256: expected_sha256.as_deref(),
+        policy: env.policy,
+    };
+    let mode = cache::FetchMode::from_flags(ctx.refresh, ctx.offline);
+    // The cache entry stays locked while `cached` is alive, until the package is in the store
+    let cached = match cache::ensure_cached(&request, mode, ctx.verbose, Some(env.multi)) {
+        Ok(cached) => cached,
         Err(e) => {
             pb.finish_with_message(format!("{} Failed to download {}", "[FAIL]".red(), name));
             return Err(e);
         }
     };
 
-    // Validate Package
-    if let Err(e) = validate_package(&cached_path, ctx.ignore_regulations) {
-        pb.finish_with_message(format!("{} Validation failed for {}", "[FAIL]".red(), name));
+    // Everything below looks only at the package directory, which is a subfolder for monorepos
+    let checkout_path = cached.path.as_path();
+    let cached_path = match package_dir(checkout_path, source.subdir.as_deref(), url) {
+        Ok(path) => path,
+        Err(e) => {
+            pb.finish_with_message(format!("{} Failed to download {}", "[FAIL]".red(), name));
+            return Err(e);
+        }
+    };
+
+    // Validate Package. Warnings are shown when a package is added; sync repeats them with --verbose
+    let package_type = match validate_package(&cached_path, ctx.ignore_regulations) {
+        Ok(report) => {
+            if !ctx.quiet && (ctx.verbose || !env.honor_lock) {
+                env.multi.suspend(|| report.print(name, ctx.verbose));
+            }
+            report.package_type
+        }
+        Err(e) => {
+            pb.finish_with_message(format!("{} Validation failed for {}", "[FAIL]".red(), name));
+            return Err(e);
+        }
+    };
+
+    let pkg_config_path = cached_path.join("finn.toml");
+    let pkg_config = if pkg_config_path.exists() {
+        Some(FinnConfig::from_file(&pkg_config_path)
+            .context(format!("Failed to parse finn.toml for {}", name))?)
+    } else {
+        None
+    };
+
+    // Enforce policy before anything lands in the project
+    let license = license::detect_package_license(&cached_path, pkg_config.as_ref());
+    if let Err(e) = env.policy.check_package_type(name, &package_type)
+        .and_then(|_| env.policy.check_license(name, license.expression.as_deref()))
+    {
+        pb.finish_with_message(format!("{} Policy check failed for {}", "[FAIL]".red(), name));
         return Err(e);
     }
 
-    // Copy to Packages Directory
-    let install_path = packages_dir.join(name);
-    if install_path.exists() {
-        if ctx.force {
-            fs::remove_dir_all(&install_path)?;
-        }
+    // Link into Packages Directory, replacing copies of another source or commit (e.g. after a
+    // branch moved, or an archive URL or hash was changed in finn.toml)
+    let commit = git::head_commit(checkout_path);
+    let stale = match locked {
+        None => true,
+        Some(p) => commit.as_ref().is_some_and(|c| *c != p.commit),
+    };
+
+    let install_path = env.packages_dir.join(name);
+    if install_path.exists() && (ctx.force || stale) {
+        fs::remove_dir_all(&install_path)?;
     }
 
     if !install_path.exists() {
-        let options = fs_extra::dir::CopyOptions::new().content_only(true);
-        if let Err(e) = fs_extra::dir::copy(&cached_path, &install_path, &options) {
-            return Err(anyhow!("Failed to copy {} from cache: {}", name, e));
+        let stored = store::store_package(&cached_path, ctx.verbose, Some(env.multi))?;
+        if let Err(e) = store::link_into(&stored.path, &install_path) {
+            pb.finish_with_message(format!("{} Failed to install {}", "[FAIL]".red(), name));
+            let _ = fs::remove_dir_all(&install_path);
+            return Err(e);
         }
     }
 
-    // Get Commit Hash
-    let output = Command::new("git")
-        .args(&["rev-parse", "HEAD"])
-        .current_dir(&install_path)
-        .output();
-    let commit_hash = match output {
-        Ok(o) if o.status.success() => String::from_utf8_lossy(&o.stdout).trim().to_string(),
-        _ => "unknown".to_string(),
+    let commit_hash = commit.unwrap_or_else(|| "unknown".to_string());
+    let submodules = if source.submodules {
+        git::submodule_commits(checkout_path, source.subdir.as_deref().unwrap_or(""))?
+    } else {
+        Default::default()
     };
+    let archive_sha256 = cached.archive_sha256.clone();
+    drop(cached);
 
-    // Calculate Checksum
-    let checksum = integrity::calculate_package_hash(&install_path)
+    // Calculate Checksum, keeping per-file digests so `finn verify` can tell what changed later.
+    // A manifest that still matches finn.lock is not replaced by one of altered files.
+    // Locked checksums in another format are checked in that format, then migrated.
+    let manifest = FileManifest::compute(&install_path, env.checksum_format)
         .context("Failed to calculate package checksum")?;
+    let matches_lock = match locked.map(|p| p.checksum.as_str()).filter(|c| !c.is_empty()) {
+        None => true,
+        Some(c) if HashFormat::of(c)? == env.checksum_format => c == manifest.checksum,
+        Some(c) => integrity::matches(&install_path, c)?,
+    };
+    if matches_lock {
+        manifest.save(env.env_path(), name)?;
+    }
+    let checksum = manifest.checksum;
 
     // Update Lockfile
-    let version_str = version.unwrap_or("HEAD").to_string();
-    lock.update(name.to_string(), url.to_string(), commit_hash, version_str, checksum);
-    
+    let mut dep_names: Vec<String> = pkg_config.as_ref()
+        .and_then(|c| c.packages.as_ref())
+        .map(|deps| deps.keys().cloned().collect())
+        .unwrap_or_default();
+    dep_names.sort();
+    let locked = LockedPackage {
+        source: url.to_string(),
+        commit: commit_hash,
+        version: version_str,
+        checksum,
+        dependencies: dep_names,
+        subdir: source.subdir.clone(),
+        sha256: archive_sha256,
+        submodules,
+    };
+
+    // Resolve dependencies here so registry lookups run in parallel too
+    let mut dependencies = Vec::new();
+    if let Some(deps) = pkg_config.and_then(|c| c.packages) {
+        for (dep_name, dep_spec) in deps {
+            validate_package_name(&dep_name)
+                .with_context(|| format!("Package '{}' declares a dependency with an invalid name", name))?;
+            let dep_src = resolve_dependency(&dep_name, &dep_spec, env.client, lock, ctx.offline)?;
+            dependencies.push((dep_name, dep_src));
+        }
+    }
+
     pb.finish_and_clear();
-    if !ctx.quiet { println!("   + Installed {}", name); }
-
-    // Install dependencies recursively
-    let pkg_config_path = install_path.join("finn.toml");
-    if pkg_config_path.exists() {
-        let pkg_config = FinnConfig::from_file(&pkg_config_path)
-            .context(format!("Failed to parse finn.toml for {}", name))?;
-
-        if let Some(deps) = pkg_config.packages {
-            for (dep_name, dep_source) in deps {
-                let dep_src = resolve_source(&dep_source, client)?;
-                install_recursive(&dep_name, &dep_src.url, dep_src.version.as_deref(), packages_dir, lock, visited, client, ctx)?;
-            }
+    if !ctx.quiet { env.multi.suspend(|| println!("   + Installed {}", name)); }
+
+    Ok(Installed { locked, dependencies })
+}
+
+/// Whether a finn.lock entry was recorded for `source`, so its commit and checksum still apply.
+pub fn describes(locked: &LockedPackage, source: &PackageSource) -> bool {
+    locked.source == source.url
+        && locked.version == source.version.as_deref().unwrap_or("HEAD")
+        && locked.subdir == source.subdir
+        && source.sha256.as_deref().is_none_or(|s| locked.sha256.as_deref().is_some_and(|l| l.eq_ignore_ascii_case(s)))
+}
+
+/// Cache entry name for a package; packages from one monorepo share a single clone.
+pub fn cache_name(name: &str, url: &str, subdir: Option<&str>) -> String {
+    match subdir {
+        Some(_) => url.trim_end_matches('/').rsplit(['/', '\\']).next().unwrap_or(name).trim_end_matches(".git").to_string(),
+        None => name.to_string(),
+    }
+}
+
+/// Resolves a `[packages]` entry from finn.toml.
+pub fn resolve_spec(spec: &DependencySpec, client: &RegistryClient) -> Result<PackageSource> {
+    match spec {
+        DependencySpec::Simple(input) => resolve_source(input, client),
+        DependencySpec::Detailed(detail) => {
+            let mut source = match (&detail.git, &detail.url) {
+                (Some(git), None) => resolve_source(git, client)?,
+                (None, Some(url)) if archive::is_archive_url(url) => resolve_source(url, client)?,
+                (None, Some(url)) => return Err(anyhow!("Unsupported archive '{}': expected a .tar.gz, .tar.xz or .zip URL.", url)),
+                _ => return Err(anyhow!("Dependency table needs exactly one of `git` or `url`.")),
+            };
+            apply_detail(&mut source, detail)?;
+            Ok(source)
         }
     }
+}
+
+/// Like `resolve_spec`, but when offline, registry names are resolved from finn.lock
+/// so that a prefetched project installs without contacting the registry.
+pub fn resolve_dependency(name: &str, spec: &DependencySpec, client: &RegistryClient, lock: &FinnLock, offline: bool) -> Result<PackageSource> {
+    let input = match spec {
+        DependencySpec::Simple(input) => Some(input),
+        DependencySpec::Detailed(detail) => detail.git.as_ref(),
+    };
+    if offline
+        && input.is_some_and(|i| is_registry_name(i))
+        && let Some(locked) = lock.packages.get(name)
+    {
+        let mut source = PackageSource {
+            name: name.to_string(),
+            url: locked.source.clone(),
+            version: Some(locked.version.clone()).filter(|v| v != "HEAD"),
+            is_official: true,
+            subdir: None,
+            submodules: false,
+            sha256: None,
+        };
+        if let DependencySpec::Detailed(detail) = spec {
+            apply_detail(&mut source, detail)?;
+        }
+        return Ok(source);
+    }
+    resolve_spec(spec, client)
+}
 
+fn apply_detail(source: &mut PackageSource, detail: &DetailedDependency) -> Result<()> {
+    source.sha256 = detail.sha256.clone();
+    if detail.version.is_some() {
+        source.version = detail.version.clone();
+    }
+    if let Some(dir) = &detail.subdir {
+        check_subdir(dir)?;
+        source.subdir = Some(dir.clone());
+    }
+    source.submodules = detail.submodules;
     Ok(())
 }
 
+pub fn is_explicit_url(input: &str) -> bool {
+    ["http", "git@", "ssh://", "git://", "file://"].iter().any(|p| input.starts_with(p))
+}
+
+pub fn is_github_shorthand(input: &str) -> bool {
+    input.contains('/') && !input.contains('\\')
+}
+
+/// Whether `resolve_source` would look `input` up in the registry.
+fn is_registry_name(input: &str) -> bool {
+    let (base, _) = split_version(input);
+    let path = Path::new(base);
+    !archive::is_archive_url(input) && !is_explicit_url(base) && !path.is_absolute() && !path.exists() && !is_github_shorthand(base)
+}
+
+/// Splits "pkg@v1.0" into the source and its version.
+pub fn split_version(input: &str) -> (&str, Option<String>) {
+    match input.split_once('@') {
+        Some((base, ver)) => (base, Some(ver.to_string())),
+        None => (input, None),
+    }
+}
+
+/// A subdir must stay inside the repository.
+fn check_subdir(dir: &str) -> Result<&Path> {
+    let path = Path::new(dir);
+    if dir.trim().is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(anyhow!("Invalid subdir '{}': must be a relative path inside the repository.", dir));
+    }
+    Ok(path)
+}
+
+/// The package directory inside a checkout of `url`: the checkout itself, or its `subdir`
+/// for monorepos. Symlinks are resolved first, so a linked subdir cannot leave the checkout.
+pub fn package_dir(checkout: &Path, subdir: Option<&str>, url: &str) -> Result<PathBuf> {
+    let Some(dir) = subdir else { return Ok(checkout.to_path_buf()) };
+    let path = checkout.join(check_subdir(dir)?);
+    if !path.is_dir() {
+        return Err(anyhow!("Subdirectory '{}' not found in '{}'.", dir, url));
+    }
+    let resolved = path.canonicalize()?;
+    if !resolved.starts_with(checkout.canonicalize()?) {
+        return Err(anyhow!("Invalid subdir '{}': it resolves to {:?}, outside the repository.", dir, resolved));
+    }
+    Ok(resolved)
+}
+
+fn subdir_name(dir: &str) -> Result<String> {
+    check_subdir(dir)?
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow!("Invalid subdir '{}'.", dir))
+}
+
 pub fn resolve_source(input: &str, client: &RegistryClient) -> Result<PackageSource> {
     // Handle Version Splitting (e.g., "pkg@v1.0")
-    let (base_input, version) = if let Some((base, ver)) = input.split_once('@') {
-        (base, Some(ver.to_string()))
-    } else {
-        (input, None)
-    };
+    let (base_input, version) = split_version(input);
+
+    // Archive downloads (.tar.gz, .tar.xz, .zip)
+    if archive::is_archive_url(input) {
+        let (name, version) = archive::name_and_version(input);
+        return Ok(PackageSource { name, url: input.to_string(), version, is_official: false, subdir: None, submodules: false, sha256: None });
+    }
 
-    // Explicit URLs (git, http, ssh, file)
-    if base_input.starts_with("http") || base_input.starts_with("git@") || base_input.starts_with("ssh://") || base_input.starts_with("file://") {
+    // Explicit URLs (git, http, ssh, git://, file)
+    if is_explicit_url(base_input) {
         let trimmed = base_input.trim_end_matches('/');
-        let name = trimmed.split('/').last().unwrap_or("package").replace(".git", "");
-        return Ok(PackageSource { name, url: base_input.to_string(), version, is_official: false });
+        let name = trimmed.split('/').next_back().unwrap_or("package").replace(".git", "");
+        return Ok(PackageSource { name, url: base_input.to_string(), version, is_official: false, subdir: None, submodules: false, sha256: None });
     }
 
     // Local Filesystem Paths
@@ -189,17 +579,17 @@ pub fn resolve_source(input: &str, client: &RegistryClient) -> Result<PackageSou
             url = url[4..].to_string();
         }
 
-        return Ok(PackageSource { name, url, version, is_official: false });
+        return Ok(PackageSource { name, url, version, is_official: false, subdir: None, submodules: false, sha256: None });
     }
 
     // GitHub Shorthand (user/repo)
-    if base_input.contains('/') && !base_input.contains('\\') {
-        let name = base_input.split('/').last().unwrap_or("package").to_string();
+    if is_github_shorthand(base_input) {
+        let name = base_input.split('/').next_back().unwrap_or("package").to_string();
         let url = format!("https://github.com/{}.git", base_input);
-        return Ok(PackageSource { name, url, version, is_official: false });
+        return Ok(PackageSource { name, url, version, is_official: false, subdir: None, submodules: false, sha256: None });
     }
     
     // Registry Lookup
     let metadata = client.get_package(input).context(format!("Failed to resolve package '{}'", input))?;
-    Ok(PackageSource { name: metadata.name, url: metadata.repo_url, version: metadata.latest_version, is_official: true })
+    Ok(PackageSource { name: metadata.name, url: metadata.repo_url, version: metadata.latest_version, is_official: true, subdir: None, submodules: false, sha256: None })
 }
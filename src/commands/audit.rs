@@ -0,0 +1,164 @@
+use crate::advisory::{self, AdvisoryDatabase, Severity};
+use crate::config::FinnConfig;
+use crate::lock::FinnLock;
+use crate::FinnContext;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use colored::*;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Vulnerability {
+    package: String,
+    version: String,
+    id: String,
+    title: String,
+    description: Option<String>,
+    severity: Severity,
+    url: Option<String>,
+    patched: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Unaudited {
+    package: String,
+    version: String,
+}
+
+#[derive(Serialize)]
+struct AuditReport {
+    database: String,
+    advisories: usize,
+    packages: usize,
+    vulnerabilities: Vec<Vulnerability>,
+    ignored: Vec<String>,
+    unaudited: Vec<Unaudited>,
+}
+
+pub fn run(db: Option<String>, json: bool, extra_ignores: Vec<String>, ctx: &FinnContext) -> Result<()> {
+    let config = FinnConfig::load()?;
+    let lock = FinnLock::load()?;
+    let audit_config = config.audit.clone().unwrap_or_default();
+
+    // CLI flag > finn.toml > environment > ~/.finn/advisory-db
+    let source = match db.or(audit_config.database).or_else(|| std::env::var("FINN_ADVISORY_DB").ok()) {
+        Some(s) => s,
+        None => advisory::default_database_dir()?.to_string_lossy().to_string(),
+    };
+    let database = AdvisoryDatabase::open(&source, ctx.verbose && !json)?;
+
+    let mut ignore_list = audit_config.ignore;
+    ignore_list.extend(extra_ignores);
+
+    if !ctx.quiet && !json {
+        println!("{} Auditing {} locked packages against {} advisories...",
+            "[INFO]".blue(), lock.packages.len(), database.advisories.len());
+    }
+
+    let mut names: Vec<&String> = lock.packages.keys().collect();
+    names.sort();
+
+    let packages_dir = Path::new(&config.project.envpath).join("packages");
+    let mut report = AuditReport {
+        database: database.path.to_string_lossy().to_string(),
+        advisories: database.advisories.len(),
+        packages: names.len(),
+        vulnerabilities: Vec::new(),
+        ignored: Vec::new(),
+        unaudited: Vec::new(),
+    };
+
+    for name in names {
+        let locked = &lock.packages[name];
+        if database.for_package(name).next().is_none() {
+            continue;
+        }
+
+        // Git refs like "HEAD" are not versions; fall back to the installed manifest
+        let version = advisory::parse_version(&locked.version)
+            .or_else(|| installed_version(&packages_dir, name));
+
+        let version = match version {
+            Some(v) => v,
+            None => {
+                report.unaudited.push(Unaudited { package: name.clone(), version: locked.version.clone() });
+                continue;
+            }
+        };
+
+        for entry in database.for_package(name) {
+            if !entry.affects(&version)? {
+                continue;
+            }
+            if ignore_list.contains(&entry.advisory.id) {
+                report.ignored.push(entry.advisory.id.clone());
+                continue;
+            }
+            report.vulnerabilities.push(Vulnerability {
+                package: name.clone(),
+                version: version.to_string(),
+                id: entry.advisory.id.clone(),
+                title: entry.advisory.title.clone(),
+                description: entry.advisory.description.clone(),
+                severity: entry.advisory.severity,
+                url: entry.advisory.url.clone(),
+                patched: entry.versions.patched.clone(),
+            });
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if !ctx.quiet {
+        print_report(&report, ctx.verbose);
+    }
+
+    if !report.vulnerabilities.is_empty() {
+        let affected: std::collections::HashSet<&str> = report.vulnerabilities.iter().map(|v| v.package.as_str()).collect();
+        return Err(anyhow!("Found {} vulnerabilities in {} packages.", report.vulnerabilities.len(), affected.len()));
+    }
+
+    Ok(())
+}
+
+fn installed_version(packages_dir: &Path, name: &str) -> Option<semver::Version> {
+    let manifest = packages_dir.join(name).join("finn.toml");
+    let pkg_config = FinnConfig::from_file(&manifest).ok()?;
+    advisory::parse_version(&pkg_config.project.version)
+}
+
+fn print_report(report: &AuditReport, verbose: bool) {
+    for vuln in &report.vulnerabilities {
+        let severity = match vuln.severity {
+            Severity::Critical | Severity::High => vuln.severity.to_string().red().bold(),
+            Severity::Medium => vuln.severity.to_string().yellow(),
+            Severity::Low => vuln.severity.to_string().normal(),
+        };
+        println!("{} {} {}: {} ({})", "[VULN]".red().bold(), vuln.package, vuln.version, vuln.id, severity);
+        println!("   {}", vuln.title);
+        if let (true, Some(desc)) = (verbose, &vuln.description) {
+            println!("   {}", desc.trim());
+        }
+        if vuln.patched.is_empty() {
+            println!("   Patched: {}", "no patched versions available".yellow());
+        } else {
+            println!("   Patched: {}", vuln.patched.join(", "));
+        }
+        if let Some(url) = &vuln.url {
+            println!("   More info: {}", url);
+        }
+    }
+
+    for pkg in &report.unaudited {
+        println!("{} '{}' is locked to '{}', which is not a version; it could not be audited.",
+            "[WARN]".yellow(), pkg.package, pkg.version);
+    }
+
+    if !report.ignored.is_empty() {
+        println!("{} Ignored advisories: {}", "[INFO]".blue(), report.ignored.join(", "));
+    }
+
+    if report.vulnerabilities.is_empty() {
+        println!("{} No known vulnerabilities found.", "[OK]".green());
+    }
+}
@@ -0,0 +1,77 @@
+use crate::bundle;
+use crate::cache::FetchMode;
+use crate::commands::fetch;
+use crate::config::FinnConfig;
+use crate::lock::FinnLock;
+use crate::policy::Policy;
+use crate::FinnContext;
+use crate::filelock;
+use crate::utils;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use colored::*;
+
+pub fn create(file: &str, ctx: &FinnContext) -> Result<()> {
+    // Relative to where the command was run; FinnConfig::load moves to the project root
+    let output = std::env::current_dir()?.join(file);
+
+    let _env_lock = filelock::project_env()?;
+    let config = FinnConfig::load()?;
+    let lock = FinnLock::load()?;
+    let policy = Policy::load()?;
+
+    let names = lock.reachable(config.packages.iter().flat_map(|p| p.keys()));
+    if names.is_empty() && config.packages.as_ref().is_some_and(|p| !p.is_empty()) {
+        return Err(anyhow!("finn.lock is missing or empty. Run 'finn sync' before creating a bundle."));
+    }
+
+    // Anything not cached yet is fetched first, so the bundle always covers the whole lockfile
+    let pb = utils::create_spinner("Collecting dependencies...", ctx.quiet);
+    let mode = FetchMode::from_flags(ctx.refresh, ctx.offline);
+    let mut keys = Vec::new();
+    for name in &names {
+        let cached = fetch::fetch_locked(name, &lock.packages[name], &policy, mode, ctx.verbose)?;
+        let key = cached.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    pb.set_message("Writing bundle...");
+    bundle::create(&output, &keys)?;
+    pb.finish_and_clear();
+
+    if !ctx.quiet {
+        let size = fs::metadata(&output).map(|m| m.len()).unwrap_or(0);
+        println!(
+            "{} Bundled {} packages ({} cache entries, {}) into {}",
+            "[OK]".green(), names.len(), keys.len(), utils::format_size(size), file
+        );
+    }
+    Ok(())
+}
+
+pub fn import(file: &str, ctx: &FinnContext) -> Result<()> {
+    let pb = utils::create_spinner(&format!("Importing {}...", file), ctx.quiet);
+    let summary = match bundle::import(Path::new(file), ctx.force) {
+        Ok(s) => s,
+        Err(e) => {
+            pb.finish_with_message(format!("{} Import failed", "[FAIL]".red()));
+            return Err(e);
+        }
+    };
+    pb.finish_and_clear();
+
+    if ctx.verbose {
+        for key in &summary.imported { println!("   + {}", key); }
+        for key in &summary.skipped { println!("   = {} (already cached)", key); }
+    }
+    if !ctx.quiet {
+        println!(
+            "{} Imported {} cache entries from {} ({} already cached).",
+            "[OK]".green(), summary.imported.len(), file, summary.skipped.len()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use crate::cache::{self, CacheIndex};
+use crate::filelock;
+use crate::integrity::{self, HashFormat};
+use crate::store;
+use crate::utils;
+use crate::FinnContext;
+use std::fs;
+use anyhow::{Result, anyhow};
+use colored::*;
+
+pub fn list(ctx: &FinnContext) -> Result<()> {
+    let index = CacheIndex::load()?;
+    if index.entries.is_empty() {
+        if !ctx.quiet { println!("{} Cache is empty.", "[INFO]".blue()); }
+        return Ok(());
+    }
+
+    let mut entries: Vec<_> = index.entries.iter().collect();
+    entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.last_used));
+
+    let now = utils::unix_now();
+    let mut total = 0;
+    println!("{:<28} {:<10} {:<10} {:<8} {:>10} {:>10}  SOURCE", "ENTRY", "REF", "COMMIT", "HISTORY", "SIZE", "LAST USED");
+    for (key, entry) in entries {
+        total += entry.size;
+        let commit = entry.commit.as_deref().map(|c| &c[..c.len().min(8)]).unwrap_or("-");
+        let history = match (&entry.commit, entry.shallow) {
+            (None, _) => "-",
+            (Some(_), true) => "shallow",
+            (Some(_), false) => "full",
+        };
+        println!("{:<28} {:<10} {:<10} {:<8} {:>10} {:>10}  {}",
+            key,
+            entry.reference.as_deref().unwrap_or("HEAD"),
+            commit,
+            history,
+            utils::format_size(entry.size),
+            format_age(now.saturating_sub(entry.last_used)),
+            entry.source);
+    }
+    println!("\n{} entries, {} total", index.entries.len(), utils::format_size(total));
+    Ok(())
+}
+
+pub fn verify(ctx: &FinnContext) -> Result<()> {
+    let index = CacheIndex::load()?;
+    let cache_root = cache::get_cache_dir()?;
+    let mut problems = 0;
+
+    for (key, entry) in &index.entries {
+        let path = cache_root.join(key);
+        if !path.exists() {
+            println!("{} {}: directory is missing", "[FAIL]".red(), key);
+            problems += 1;
+            continue;
+        }
+        let current = integrity::package_hash(&path, HashFormat::of(&entry.checksum)?)?;
+        if current != entry.checksum {
+            println!("{} {}: contents changed since last use", "[FAIL]".red(), key);
+            if ctx.verbose {
+                println!("   Expected: {}\n   Actual:   {}", entry.checksum, current);
+            }
+            problems += 1;
+        } else if ctx.verbose {
+            println!("{} {}", "[OK]".green(), key);
+        }
+    }
+
+    // Directories nobody recorded (e.g. from older finn versions) cannot be verified
+    for dir in fs::read_dir(&cache_root)?.filter_map(|e| e.ok()) {
+        let key = dir.file_name().to_string_lossy().to_string();
+        if !index.entries.contains_key(&key) && !ctx.quiet {
+            println!("{} {}: not in cache index (run 'finn cache prune' to remove untracked entries)", "[WARN]".yellow(), key);
+        }
+    }
+
+    // Store entries are named by checksum; editing a hardlinked project file changes them too
+    for checksum in store::corrupted_entries()? {
+        println!("{} store/{}: contents no longer match the checksum (was a linked project file edited?)", "[FAIL]".red(), store::short(&checksum));
+        problems += 1;
+    }
+
+    if problems > 0 {
+        return Err(anyhow!("{} cache entries failed verification. Run 'finn cache clean' or remove them to re-download.", problems));
+    }
+    if !ctx.quiet { println!("{} Verified {} cache entries.", "[OK]".green(), index.entries.len()); }
+    Ok(())
+}
+
+pub fn prune(older_than: Option<String>, ctx: &FinnContext) -> Result<()> {
+    let _index_lock = filelock::cache_index(None)?;
+    let mut index = CacheIndex::load()?;
+    let cache_root = cache::get_cache_dir()?;
+    let now = utils::unix_now();
+    let max_age = match older_than {
+        Some(d) => Some(utils::parse_duration(&d)?.as_secs()),
+        None => None,
+    };
+
+    let stale: Vec<String> = index.entries.iter()
+        .filter(|(key, entry)| {
+            !cache_root.join(key).exists()
+                || max_age.is_some_and(|age| now.saturating_sub(entry.last_used) > age)
+        })
+        .map(|(key, _)| key.clone())
+        .collect();
+
+    let mut freed = 0;
+    let mut removed = 0;
+    for key in &stale {
+        let Some(_entry_lock) = try_lock_entry(key, ctx)? else { continue };
+        if let Some(entry) = index.entries.remove(key) {
+            freed += entry.size;
+        }
+        cache::remove_entry(&cache_root, key)?;
+        removed += 1;
+        if ctx.verbose { println!("   Removed {}", key); }
+    }
+
+    // Untracked directories have no usage data, so they are always pruned
+    let mut untracked = 0;
+    for dir in fs::read_dir(&cache_root)?.filter_map(|e| e.ok()) {
+        let key = dir.file_name().to_string_lossy().to_string();
+        if !index.entries.contains_key(&key) {
+            let Some(_entry_lock) = try_lock_entry(&key, ctx)? else { continue };
+            freed += cache::dir_size(&dir.path());
+            cache::remove_entry(&cache_root, &key)?;
+            untracked += 1;
+        }
+    }
+
+    index.save()?;
+    if !ctx.quiet {
+        println!("{} Pruned {} entries ({} untracked), freed {}.",
+            "[OK]".green(), removed + untracked, untracked, utils::format_size(freed));
+    }
+    Ok(())
+}
+
+pub fn clean(ctx: &FinnContext) -> Result<()> {
+    let _index_lock = filelock::cache_index(None)?;
+    let mut index = CacheIndex::load()?;
+    let cache_root = cache::get_cache_dir()?;
+    let mut freed = 0;
+    let mut in_use = 0;
+
+    for dir in fs::read_dir(&cache_root)?.filter_map(|e| e.ok()) {
+        let key = dir.file_name().to_string_lossy().to_string();
+        let Some(_entry_lock) = try_lock_entry(&key, ctx)? else {
+            in_use += 1;
+            continue;
+        };
+        freed += cache::dir_size(&dir.path());
+        cache::remove_entry(&cache_root, &key)?;
+        index.entries.remove(&key);
+    }
+    if in_use == 0 {
+        index.entries.clear();
+    }
+    index.save()?;
+
+    // Installed projects keep their own links or copies, so store entries can always be dropped
+    for dir in fs::read_dir(store::get_store_dir()?)?.filter_map(|e| e.ok()) {
+        let name = dir.file_name().to_string_lossy().to_string();
+        let Some(_entry_lock) = try_lock_store_entry(&name, ctx)? else { continue };
+        freed += cache::dir_size(&dir.path());
+        fs::remove_dir_all(dir.path())?;
+    }
+
+    if !ctx.quiet { println!("{} Removed all cached packages, freed {}.", "[OK]".green(), utils::format_size(freed)); }
+    Ok(())
+}
+
+/// Entries being installed by another finn process are left alone.
+fn try_lock_entry(key: &str, ctx: &FinnContext) -> Result<Option<filelock::FileLock>> {
+    let lock = filelock::try_cache_entry(key)?;
+    if lock.is_none() && !ctx.quiet {
+        println!("{} {}: in use by another finn process, skipped", "[WARN]".yellow(), key);
+    }
+    Ok(lock)
+}
+
+/// Store entries that another finn process is creating or installing from are left alone.
+fn try_lock_store_entry(name: &str, ctx: &FinnContext) -> Result<Option<filelock::FileLock>> {
+    let lock = filelock::try_store_entry(&store::dir_checksum(name))?;
+    if lock.is_none() && !ctx.quiet {
+        println!("{} store/{}: in use by another finn process, skipped", "[WARN]".yellow(), name);
+    }
+    Ok(lock)
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        s if s < 60 => "just now".to_string(),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86_400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86_400),
+    }
+}
@@ -0,0 +1,100 @@
+use crate::config::{DependencySpec, FinnConfig};
+use crate::commands::add;
+use crate::registry::RegistryClient;
+use crate::{archive, cache, git};
+use crate::validator::{self, Severity};
+use crate::FinnContext;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use colored::*;
+
+/// Checks that the current package is ready to publish: its finn.toml metadata, that every
+/// dependency resolves the same way for its users, and the validation `finn add` applies.
+/// Fails on errors, and with `strict` on warnings too.
+pub fn run(strict: bool, ctx: &FinnContext) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let manifest = FinnConfig::find_manifest(&cwd)
+        .ok_or_else(|| anyhow!("Could not find `finn.toml` in {:?} or any parent directory.", cwd))?;
+    let root = manifest.parent().unwrap_or(&cwd);
+    let config = FinnConfig::from_file(&manifest).ok();
+    let name = config.as_ref()
+        .map(|c| c.project.name.clone())
+        .unwrap_or_else(|| root.file_name().unwrap_or_default().to_string_lossy().to_string());
+
+    let mut report = validator::check_release(root);
+    let mut dependencies: Vec<_> = config.iter().flat_map(|c| c.packages.iter().flatten()).collect();
+    dependencies.sort_by(|a, b| a.0.cmp(b.0));
+    let client = RegistryClient::new(None);
+    for (dependency, spec) in dependencies {
+        if let Some(problem) = unpublishable(dependency, spec, root, &client) {
+            report.add(Severity::Error, format!("Dependency '{}' {}.", dependency, problem));
+        }
+    }
+
+    if !ctx.quiet {
+        report.print(&name, ctx.verbose);
+    }
+
+    let errors = report.errors().count();
+    let warnings = report.warnings().count();
+    if errors > 0 || (strict && warnings > 0) {
+        return Err(anyhow!("'{}' is not ready to publish: {} errors and {} warnings.", name, errors, warnings));
+    }
+    if !ctx.quiet {
+        match warnings {
+            0 => println!("{} {}: no problems found.", "[OK]".green(), name),
+            n => println!("{} {}: passed with {} warnings (use --strict to treat them as errors).", "[OK]".green(), name, n),
+        }
+    }
+    Ok(())
+}
+
+/// Why a dependency would not resolve to the same code for the package's users, if it wouldn't.
+fn unpublishable(dependency: &str, spec: &DependencySpec, root: &Path, client: &RegistryClient) -> Option<String> {
+    let (input, version) = match spec {
+        DependencySpec::Simple(input) => add::split_version(input),
+        DependencySpec::Detailed(detail) => (detail.git.as_deref()?, detail.version.clone()),
+    };
+    if archive::is_archive_url(input) {
+        return None;
+    }
+
+    let path = Path::new(input.strip_prefix("file://").unwrap_or(input));
+    if input.starts_with("file://") || input.starts_with('.') || path.is_absolute() || root.join(path).exists() {
+        return Some(format!("is a local path ('{}') that only exists on this machine", input));
+    }
+    if !add::is_explicit_url(input) && !add::is_github_shorthand(input) {
+        return None; // registry package
+    }
+    match version {
+        None => Some(format!("follows the default branch of '{}'; pin a tag or commit", input)),
+        Some(version) if is_pinned(&version, cached_checkout(dependency, spec, client).as_deref()) => None,
+        Some(version) if is_commit_prefix(&version) => {
+            Some(format!("refers to the commit '{}' by an abbreviated id; pin the full commit id", version))
+        }
+        Some(version) => Some(format!("follows the git branch '{}'; pin a tag or commit", version)),
+    }
+}
+
+/// Whether a git ref never moves. It is looked up among the tags and commits of the cached
+/// repository when the dependency was installed before; otherwise only a semantic version tag
+/// (`v1.2.0`) or a full commit id counts, since a branch may be called `1.x` or `2-stable`.
+fn is_pinned(version: &str, cached: Option<&Path>) -> bool {
+    if let Some(repo) = cached {
+        return git::is_immutable_ref_present(repo, version);
+    }
+    let tag = version.strip_prefix('v').unwrap_or(version);
+    semver::Version::parse(tag).is_ok() || (version.len() == 40 && is_commit_prefix(version))
+}
+
+fn is_commit_prefix(version: &str) -> bool {
+    (7..=40).contains(&version.len()) && version.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The cache entry `finn add` or `finn fetch` cloned a git dependency into, if there is one.
+fn cached_checkout(dependency: &str, spec: &DependencySpec, client: &RegistryClient) -> Option<PathBuf> {
+    let source = add::resolve_spec(spec, client).ok()?;
+    let name = add::cache_name(dependency, &source.url, source.subdir.as_deref());
+    let path = cache::get_cache_dir().ok()?.join(cache::cache_key(&name, &source.url, source.version.as_deref()));
+    path.join(".git").exists().then_some(path)
+}
@@ -18,7 +18,7 @@ pub fn run(_ctx: &FinnContext) -> Result<()> {
 
     for entry in walkdir::WalkDir::new(".") {
         let entry = entry?;
-        if entry.path().extension().map_or(false, |e| e == "o" || e == "obj") {
+        if entry.path().extension().is_some_and(|e| e == "o" || e == "obj") {
             fs::remove_file(entry.path())?;
         }
     }
@@ -0,0 +1,99 @@
+use crate::config::{DependencySpec, FinnConfig};
+use crate::commands::add;
+use crate::lock::{FinnLock, LockedPackage};
+use crate::FinnContext;
+use crate::cache::{self, CacheRequest, CachedSource, FetchMode};
+use crate::filelock;
+use crate::policy::Policy;
+use crate::registry::RegistryClient;
+use crate::utils;
+use crate::validator::validate_package_name;
+use std::collections::HashSet;
+use anyhow::{Result, Context};
+use colored::*;
+
+/// Downloads every dependency into the cache without installing anything or changing finn.lock.
+pub fn run(ctx: &FinnContext) -> Result<()> {
+    let _env_lock = filelock::project_env()?;
+    let config = FinnConfig::load()?;
+    let lock = FinnLock::load()?;
+    let policy = Policy::load()?;
+    let registry_url = config.registry.as_ref().map(|r| r.url.clone());
+    let client = RegistryClient::new(registry_url);
+    let mode = FetchMode::from_flags(ctx.refresh, ctx.offline);
+
+    let pb = utils::create_spinner("Fetching dependencies...", ctx.quiet);
+    let mut fetched = 0;
+
+    let roots: Vec<&String> = config.packages.iter().flat_map(|p| p.keys()).collect();
+    for name in lock.reachable(roots.iter().copied()) {
+        fetch_locked(&name, &lock.packages[&name], &policy, mode, ctx.verbose)?;
+        fetched += 1;
+        pb.suspend(|| {
+            if !ctx.quiet { println!("   + Fetched {}", name); }
+        });
+    }
+
+    // Entries not locked yet are resolved as `finn add` would, along with their dependencies
+    let mut pending: Vec<(String, DependencySpec)> = config.packages.iter().flatten()
+        .filter(|(name, _)| !lock.packages.contains_key(*name))
+        .map(|(name, spec)| (name.clone(), spec.clone()))
+        .collect();
+    let mut seen = HashSet::new();
+
+    while let Some((name, spec)) = pending.pop() {
+        if lock.packages.contains_key(&name) || !seen.insert(name.clone()) {
+            continue;
+        }
+        validate_package_name(&name)?;
+        let source = add::resolve_spec(&spec, &client)?;
+        policy.check_package(&name)?;
+        policy.check_source(&name, &source.url)?;
+
+        let cache_name = add::cache_name(&name, &source.url, source.subdir.as_deref());
+        let request = CacheRequest {
+            name: &cache_name,
+            url: &source.url,
+            version: source.version.as_deref(),
+            pinned: None,
+            submodules: source.submodules,
+            sha256: source.sha256.as_deref(),
+            policy: &policy,
+        };
+        let cached = cache::ensure_cached(&request, mode, ctx.verbose, None).with_context(|| format!("Failed to fetch '{}'", name))?;
+
+        let package_dir = add::package_dir(&cached.path, source.subdir.as_deref(), &source.url)?;
+        if let Ok(pkg_config) = FinnConfig::from_file(&package_dir.join("finn.toml")) {
+            pending.extend(pkg_config.packages.into_iter().flatten());
+        }
+
+        fetched += 1;
+        pb.suspend(|| {
+            if !ctx.quiet { println!("   + Fetched {} (not locked)", name); }
+        });
+    }
+
+    pb.finish_and_clear();
+    if !ctx.quiet {
+        println!("{} Fetched {} packages into the cache.", "[OK]".green(), fetched);
+    }
+    Ok(())
+}
+
+/// Caches a locked package at exactly the state `finn sync` will install.
+pub fn fetch_locked(name: &str, locked: &LockedPackage, policy: &Policy, mode: FetchMode, verbose: bool) -> Result<CachedSource> {
+    policy.check_package(name)?;
+    policy.check_source(name, &locked.source)?;
+
+    let cache_name = add::cache_name(name, &locked.source, locked.subdir.as_deref());
+    let request = CacheRequest {
+        name: &cache_name,
+        url: &locked.source,
+        version: Some(locked.version.as_str()).filter(|v| *v != "HEAD"),
+        pinned: Some(locked.commit.as_str()).filter(|c| *c != "unknown"),
+        submodules: !locked.submodules.is_empty(),
+        sha256: locked.sha256.as_deref(),
+        policy,
+    };
+    cache::ensure_cached(&request, mode, verbose, None).with_context(|| format!("Failed to fetch '{}'", name))
+}
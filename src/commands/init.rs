@@ -1,5 +1,6 @@
 use crate::config::FinnConfig;
 use crate::FinnContext;
+use crate::git;
 use crate::utils;
 use std::fs;
 use std::path::Path;
@@ -93,27 +94,16 @@ pub fn run(path: &str, yes: bool, name_arg: Option<String>, template_arg: Option
     fs::write(root.join(".gitignore"), ".finn/\nout/\n*.o\n*.exe\n")?;
 
     if use_git {
-        // Initialize git repository with initial commit
-        std::process::Command::new("git")
-            .arg("init")
-            .current_dir(root)
-            .output()
-            .ok();
-            
-        std::process::Command::new("git")
-            .arg("add")
-            .arg(".")
-            .current_dir(root)
-            .output()
-            .ok();
-            
-        std::process::Command::new("git")
-            .arg("commit")
-            .arg("-m")
-            .arg("Initial commit")
-            .current_dir(root)
-            .output()
-            .ok();
+        // Initialize git repository with initial commit (skipped without a configured identity)
+        match git::init_project(root) {
+            Ok(true) => {}
+            Ok(false) => if !ctx.quiet {
+                pb.suspend(|| println!("{} Initialized git repository without a commit: set user.name and user.email to commit.", "[WARN]".yellow()));
+            },
+            Err(e) => if !ctx.quiet {
+                pb.suspend(|| println!("{} Could not initialize git repository: {}", "[WARN]".yellow(), e));
+            },
+        }
     }
 
     pb.finish_and_clear();
@@ -1,8 +1,12 @@
+use crate::cache;
+use crate::config::FinnConfig;
+use crate::license;
 use crate::FinnContext;
 use crate::utils;
+use crate::validator::{validate_package, validate_package_name};
 use std::process::Command;
 use std::fs;
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, Context};
 use colored::*;
 use tempfile::TempDir;
 
@@ -10,6 +14,12 @@ pub fn run(package_ref: &str, ctx: &FinnContext) -> Result<()> {
     // Initialize Registry Client
     let client = crate::registry::RegistryClient::new(None);
     let source = crate::commands::add::resolve_source(package_ref, &client)?;
+    validate_package_name(&source.name)?;
+
+    let policy = crate::policy::Policy::load()?;
+    policy.check_package(&source.name)?;
+    policy.check_source(&source.name, &source.url)?;
+    policy.check_depth(&source.name, 1)?;
 
     if !source.is_official && !ctx.ignore_regulations {
         return Err(anyhow!("Security Error: Cannot install binary from unofficial source '{}' without --ignore-regulations.", source.url));
@@ -17,15 +27,33 @@ pub fn run(package_ref: &str, ctx: &FinnContext) -> Result<()> {
 
     if !ctx.quiet { println!("{} Installing binary '{}'...", "[INFO]".blue(), source.name); }
 
+    // Fetch through the package cache, then build in a scratch copy
+    let request = cache::CacheRequest {
+        name: &source.name,
+        url: &source.url,
+        version: source.version.as_deref(),
+        pinned: None,
+        submodules: false,
+        sha256: None,
+        policy: &policy,
+    };
+    let mode = cache::FetchMode::from_flags(ctx.refresh, ctx.offline);
+    let cached = cache::ensure_cached(&request, mode, ctx.verbose, None)
+        .with_context(|| format!("Failed to fetch '{}'", source.url))?;
+
     let temp_dir = TempDir::new()?;
     let repo_path = temp_dir.path().join(&source.name);
-    
-    Command::new("git").arg("clone").arg(&source.url).arg(&repo_path).output()?;
+    let options = fs_extra::dir::CopyOptions::new().content_only(true);
+    fs_extra::dir::copy(&cached.path, &repo_path, &options)
+        .map_err(|e| anyhow!("Failed to prepare build directory: {}", e))?;
+    drop(cached);
 
-    // Checkout version if specified
-    if let Some(ver) = source.version {
-        Command::new("git").arg("checkout").arg(ver).current_dir(&repo_path).output()?;
-    }
+    // Enforce the same policy as for dependencies before running anything from the package
+    let package_type = validate_package(&repo_path, ctx.ignore_regulations)?.package_type;
+    let pkg_config = FinnConfig::from_file(&repo_path.join("finn.toml")).ok();
+    let license = license::detect_package_license(&repo_path, pkg_config.as_ref());
+    policy.check_package_type(&source.name, &package_type)?;
+    policy.check_license(&source.name, license.expression.as_deref())?;
 
     if !ctx.quiet { println!("   Building..."); }
     let compiler = crate::utils::find_compiler()?;
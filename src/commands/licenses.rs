@@ -0,0 +1,144 @@
+use crate::config::FinnConfig;
+use crate::license::{self, LicenseOrigin};
+use crate::lock::FinnLock;
+use crate::FinnContext;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow, Context};
+use colored::*;
+use serde::Serialize;
+
+const UNKNOWN: &str = "UNKNOWN";
+
+#[derive(Serialize)]
+struct DependencyLicense {
+    name: String,
+    version: String,
+    source: String,
+    license: Option<String>,
+    origin: LicenseOrigin,
+    license_files: Vec<String>,
+    #[serde(skip)]
+    texts: Vec<(PathBuf, String)>,
+}
+
+pub fn run(json: bool, notice: Option<String>, extra_deny: Vec<String>, ctx: &FinnContext) -> Result<()> {
+    let config = FinnConfig::load()?;
+    let lock = FinnLock::load()?;
+    let packages_dir = Path::new(&config.project.envpath).join("packages");
+
+    let mut deny = config.licenses.as_ref().map(|l| l.deny.clone()).unwrap_or_default();
+    deny.extend(extra_deny);
+
+    let dependencies = collect(&packages_dir, &lock);
+
+    // SPDX expression -> package names
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for dep in &dependencies {
+        let key = dep.license.clone().unwrap_or_else(|| UNKNOWN.to_string());
+        groups.entry(key).or_default().push(dep.name.clone());
+    }
+
+    if let Some(path) = &notice {
+        fs::write(path, render_notice(&config, &dependencies))
+            .with_context(|| format!("Failed to write notice file '{}'", path))?;
+    }
+
+    if json {
+        let report = serde_json::json!({
+            "project": config.project.name,
+            "license": config.project.license,
+            "packages": dependencies,
+            "groups": groups,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if !ctx.quiet {
+        println!("{} Collected licenses for {} packages:", "[INFO]".blue(), dependencies.len());
+        for (expr, names) in &groups {
+            let label = if expr == UNKNOWN { expr.red().bold() } else { expr.bold() };
+            println!("{} ({})", label, names.len());
+            for name in names {
+                let dep = dependencies.iter().find(|d| &d.name == name).unwrap();
+                println!("   - {} {}", dep.name, dep.version);
+            }
+        }
+        if let Some(path) = &notice {
+            println!("{} Wrote third-party notices to {}", "[OK]".green(), path);
+        }
+    }
+
+    let unknown: Vec<&str> = dependencies.iter()
+        .filter(|d| d.license.is_none())
+        .map(|d| d.name.as_str())
+        .collect();
+    if !unknown.is_empty() && !ctx.quiet && !json {
+        println!("{} No recognizable license for: {}", "[WARN]".yellow(), unknown.join(", "));
+    }
+
+    // A package fails if it cannot avoid a denied license (an OR branch without one is fine)
+    let denied: Vec<String> = dependencies.iter()
+        .filter_map(|d| {
+            let expr = d.license.as_deref()?;
+            let acceptable = license::expression_satisfies(expr, |id| !deny.iter().any(|x| x.eq_ignore_ascii_case(id)));
+            (!acceptable).then(|| format!("'{}' ({})", d.name, expr))
+        })
+        .collect();
+
+    if !denied.is_empty() {
+        return Err(anyhow!("Denied licenses found: {}", denied.join(", ")));
+    }
+
+    Ok(())
+}
+
+fn collect(packages_dir: &Path, lock: &FinnLock) -> Vec<DependencyLicense> {
+    let mut names: Vec<&String> = lock.packages.keys().collect();
+    names.sort();
+
+    names.into_iter().map(|name| {
+        let locked = &lock.packages[name];
+        let root = packages_dir.join(name);
+        let pkg_config = FinnConfig::from_file(&root.join("finn.toml")).ok();
+        let detected = license::detect_package_license(&root, pkg_config.as_ref());
+
+        let texts = detected.files.iter()
+            .filter_map(|f| fs::read_to_string(f).ok().map(|t| (f.clone(), t)))
+            .collect();
+
+        DependencyLicense {
+            name: name.clone(),
+            version: pkg_config.map(|c| c.project.version).unwrap_or_else(|| locked.version.clone()),
+            source: locked.source.clone(),
+            license: detected.expression,
+            origin: detected.origin,
+            license_files: detected.files.iter()
+                .filter_map(|f| f.strip_prefix(&root).ok())
+                .map(|f| f.to_string_lossy().replace("\\", "/"))
+                .collect(),
+            texts,
+        }
+    }).collect()
+}
+
+fn render_notice(config: &FinnConfig, dependencies: &[DependencyLicense]) -> String {
+    let rule = "=".repeat(72);
+    let thin = "-".repeat(72);
+    let mut out = String::new();
+
+    out.push_str("THIRD-PARTY SOFTWARE NOTICES\n\n");
+    out.push_str(&format!("{} {} includes the following third-party packages.\n", config.project.name, config.project.version));
+
+    for dep in dependencies {
+        out.push_str(&format!("\n{}\n{} {}\n", rule, dep.name, dep.version));
+        out.push_str(&format!("License: {}\n", dep.license.as_deref().unwrap_or(UNKNOWN)));
+        out.push_str(&format!("Source: {}\n", dep.source));
+
+        for (path, text) in &dep.texts {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            out.push_str(&format!("{}\n{}:\n\n{}\n", thin, file_name, text.trim_end()));
+        }
+    }
+
+    out
+}
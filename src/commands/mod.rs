@@ -1,12 +1,16 @@
 pub mod add;
+pub mod audit;
 pub mod build;
+pub mod cache;
 pub mod clean;
 pub mod download;
 pub mod healthcheck;
 pub mod init;
 pub mod install;
+pub mod licenses;
 pub mod remove;
 pub mod run;
+pub mod sbom;
 pub mod sync;
 pub mod task;
 pub mod test;
@@ -1,6 +1,9 @@
 use crate::config::FinnConfig;
 use crate::FinnContext;
 use crate::utils;
+use crate::filelock;
+use crate::integrity::FileManifest;
+use crate::validator::validate_package_name;
 use std::path::Path;
 use std::fs;
 use anyhow::{Context, Result, anyhow};
@@ -8,16 +11,21 @@ use colored::*;
 
 // Changed _ctx to ctx so we can use it
 pub fn run(package_ref: &str, ctx: &FinnContext) -> Result<()> {
+    let _env_lock = filelock::project_env()?;
     // FIX: Pass ctx.quiet to create_spinner
     let pb = utils::create_spinner(&format!("Removing {}...", package_ref), ctx.quiet);
 
     let mut config = FinnConfig::load()?;
 
     let package_name = if package_ref.contains('/') {
-        package_ref.split('/').last().unwrap()
+        package_ref.split('/').next_back().unwrap()
     } else {
         package_ref
     };
+    if let Err(e) = validate_package_name(package_name) {
+        pb.finish_and_clear();
+        return Err(e);
+    }
 
     let removed_from_config = if let Some(packages) = &mut config.packages {
         packages.remove(package_name).is_some()
@@ -36,6 +44,7 @@ pub fn run(package_ref: &str, ctx: &FinnContext) -> Result<()> {
     if package_dir.exists() {
         fs::remove_dir_all(&package_dir).context("Failed to delete package directory")?;
     }
+    let _ = fs::remove_file(FileManifest::path(env_path, package_name));
 
     config.save()?;
 
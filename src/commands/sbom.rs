@@ -0,0 +1,33 @@
+use crate::config::FinnConfig;
+use crate::lock::FinnLock;
+use crate::sbom::{self, Inventory};
+use crate::FinnContext;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, anyhow, Context};
+use colored::*;
+
+pub fn run(format: &str, output: Option<String>, ctx: &FinnContext) -> Result<()> {
+    let config = FinnConfig::load()?;
+    let lock = FinnLock::load()?;
+
+    if lock.packages.is_empty() && config.packages.as_ref().is_some_and(|p| !p.is_empty()) {
+        return Err(anyhow!("finn.lock is missing or empty. Run 'finn sync' before generating an SBOM."));
+    }
+
+    let packages_dir = Path::new(&config.project.envpath).join("packages");
+    let inventory = Inventory::collect(&config, &lock, &packages_dir);
+    let document = sbom::render(format, &inventory)?;
+
+    match output {
+        Some(path) => {
+            fs::write(&path, document).with_context(|| format!("Failed to write SBOM to '{}'", path))?;
+            if !ctx.quiet {
+                println!("{} Wrote {} SBOM for {} packages to {}", "[OK]".green(), format, lock.packages.len(), path);
+            }
+        }
+        None => print!("{}", document),
+    }
+
+    Ok(())
+}
@@ -3,18 +3,42 @@ use crate::commands::add;
 use crate::lock::FinnLock;
 use crate::FinnContext;
 use crate::utils;
-use crate::integrity;
+use crate::filelock;
+use crate::integrity::{self, FileManifest, HashFormat};
+use crate::policy::Policy;
+use crate::license;
+use crate::store;
+use crate::vendor;
+use crate::validator::validate_package;
 use std::path::Path;
-use std::collections::HashSet;
 use std::fs;
 use anyhow::{Result, anyhow};
 use colored::*;
+use indicatif::MultiProgress;
 
 pub fn run(ctx: &FinnContext) -> Result<()> {
-    let pb = utils::create_spinner("Reading configuration...", ctx.quiet);
-    
+    let _env_lock = filelock::project_env()?;
     let config = FinnConfig::load()?;
+
+    match &config.vendor {
+        Some(vendor) => sync_vendored(&config, Path::new(&vendor.directory), ctx)?,
+        None => install_locked(&config, ctx)?,
+    }
+
+    if !ctx.quiet {
+        println!("{} Sync complete. Integrity verified.", "[OK]".green());
+    }
+    Ok(())
+}
+
+/// Installs every package in finn.toml, at the commits recorded in finn.lock.
+/// The caller must hold the project environment lock.
+pub fn install_locked(config: &FinnConfig, ctx: &FinnContext) -> Result<()> {
+    let multi = MultiProgress::new();
+    let pb = multi.add(utils::create_spinner("Reading configuration...", ctx.quiet));
+
     let mut lock = FinnLock::load()?;
+    let policy = Policy::load()?;
     let env_path = Path::new(&config.project.envpath);
     let packages_dir = env_path.join("packages");
     
@@ -26,45 +50,54 @@ pub fn run(ctx: &FinnContext) -> Result<()> {
 
     pb.set_message("Syncing dependencies...");
 
-    let mut visited = HashSet::new();
-
-    if let Some(packages) = config.packages {
-        for (name, source) in packages {
-            // Resolve source to get URL/Version
-            let pkg_source = add::resolve_source(&source, &client)?;
-            
-            // FIX: Capture expected checksum from lockfile BEFORE install updates it
-            let expected_checksum = lock.packages.get(&name).map(|p| p.checksum.clone());
-
-            pb.suspend(|| {
-                if !ctx.quiet { println!("{} Syncing '{}'...", "[INFO]".blue(), name); }
-            });
-
-            // Install (Recursive)
-            add::install_recursive(
-                &name, 
-                &pkg_source.url, 
-                pkg_source.version.as_deref(), // Pass version
-                &packages_dir, 
-                &mut lock, 
-                &mut visited, 
-                &client,
-                ctx
-            )?;
-
-            // VERIFY INTEGRITY
-            if let Some(expected) = expected_checksum {
-                if !expected.is_empty() {
-                    let installed_path = packages_dir.join(&name);
-                    let current_hash = integrity::calculate_package_hash(&installed_path)?;
-                    
-                    if current_hash != expected {
-                        return Err(anyhow!(
-                            "Integrity Check Failed for '{}'!\nExpected: {}\nActual:   {}\nSecurity Warning: The package contents have changed since they were locked.",
-                            name, expected, current_hash
-                        ));
-                    }
-                }
+    let env = add::InstallEnv { packages_dir: &packages_dir, client: &client, policy: &policy, ctx, honor_lock: true, multi: &multi, checksum_format: config.checksum_format() };
+
+    let mut roots = Vec::new();
+    let mut expected_checksums = Vec::new();
+    let mut names: Vec<&String> = config.packages.iter().flat_map(|p| p.keys()).collect();
+    names.sort();
+    for name in names {
+        let source = &config.packages.as_ref().unwrap()[name];
+
+        // Resolve source to get URL/Version
+        let pkg_source = add::resolve_dependency(name, source, &client, &lock, ctx.offline)?;
+
+        // FIX: Capture expected checksum from lockfile BEFORE install updates it.
+        // An entry for a source that was since changed in finn.toml is replaced instead.
+        let locked = lock.packages.get(name).filter(|p| add::describes(p, &pkg_source));
+        expected_checksums.push((name, locked.map(|p| p.checksum.clone())));
+
+        multi.suspend(|| {
+            if !ctx.quiet { println!("{} Syncing '{}'...", "[INFO]".blue(), name); }
+        });
+        roots.push((name.clone(), pkg_source));
+    }
+
+    // Checksums in an older format are verified as they are, then rewritten by the install
+    let format = config.checksum_format();
+    let outdated: Vec<String> = lock.packages.iter()
+        .filter(|(_, p)| !p.checksum.is_empty() && HashFormat::of(&p.checksum).ok() != Some(format))
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    // Install (Recursive, in parallel)
+    add::install_packages(roots, &env, &mut lock)?;
+
+    // VERIFY INTEGRITY
+    for (name, expected) in expected_checksums {
+        if let Some(expected) = expected.filter(|c| !c.is_empty()) {
+            let installed_path = packages_dir.join(name);
+            let current = FileManifest::compute(&installed_path, HashFormat::of(&expected)?)?;
+
+            if current.checksum != expected {
+                let changed = FileManifest::load(env_path, name)
+                    .filter(|recorded| recorded.checksum == expected)
+                    .map(|recorded| format!("\nChanged files:\n{}", recorded.diff(&current).lines().join("\n")))
+                    .unwrap_or_default();
+                return Err(anyhow!(
+                    "Integrity Check Failed for '{}'!\nExpected: {}\nActual:   {}{}\nSecurity Warning: The package contents have changed since they were locked.",
+                    name, expected, current.checksum, changed
+                ));
             }
         }
     }
@@ -72,8 +105,72 @@ pub fn run(ctx: &FinnContext) -> Result<()> {
     lock.save()?;
 
     pb.finish_and_clear();
-    if !ctx.quiet {
-        println!("{} Sync complete. Integrity verified.", "[OK]".green());
+    let migrated = outdated.iter()
+        .filter(|name| lock.packages.get(*name).is_some_and(|p| HashFormat::of(&p.checksum).ok() == Some(format)))
+        .count();
+    if migrated > 0 && !ctx.quiet {
+        println!("{} Migrated {} finn.lock checksums to the {} format.", "[INFO]".blue(), migrated, format.name());
     }
     Ok(())
 }
+
+/// Installs the locked dependency tree from the `finn vendor` directory, without network or cache.
+fn sync_vendored(config: &FinnConfig, vendor_dir: &Path, ctx: &FinnContext) -> Result<()> {
+    let lock = FinnLock::load()?;
+    let policy = Policy::load()?;
+    let env_path = Path::new(&config.project.envpath);
+    let packages_dir = env_path.join("packages");
+
+    if !vendor_dir.is_dir() {
+        return Err(anyhow!("Vendor directory {:?} not found. Run 'finn vendor' to create it.", vendor_dir));
+    }
+    for name in config.packages.iter().flat_map(|p| p.keys()) {
+        if !lock.packages.contains_key(name) {
+            return Err(anyhow!("'{}' is not in finn.lock, so it cannot be installed from {:?}. Run 'finn vendor' again.", name, vendor_dir));
+        }
+    }
+    if !packages_dir.exists() { fs::create_dir_all(&packages_dir)?; }
+
+    let pb = utils::create_spinner("Installing vendored packages...", ctx.quiet);
+    for name in lock.reachable(config.packages.iter().flat_map(|p| p.keys())) {
+        let name = name.as_str();
+        let locked = &lock.packages[name];
+        policy.check_package(name)?;
+        policy.check_source(name, &locked.source)?;
+        policy.check_checksum(name, Some(&locked.checksum))?;
+
+        let vendored = vendor_dir.join(name);
+        if !vendored.is_dir() {
+            return Err(anyhow!("'{}' is missing from {:?}. Run 'finn vendor' again.", name, vendor_dir));
+        }
+        let staged = vendor::stage_package(&vendored, &locked.checksum)?;
+
+        let report = validate_package(staged.path(), ctx.ignore_regulations)?;
+        if ctx.verbose { pb.suspend(|| report.print(name, true)); }
+        let package_type = report.package_type;
+        let pkg_config = FinnConfig::from_file(&staged.path().join("finn.toml")).ok();
+        let license = license::detect_package_license(staged.path(), pkg_config.as_ref());
+        policy.check_package_type(name, &package_type)?;
+        policy.check_license(name, license.expression.as_deref())?;
+
+        let install_path = packages_dir.join(name);
+        if install_path.exists() && (ctx.force || !integrity::matches(&install_path, &locked.checksum)?) {
+            fs::remove_dir_all(&install_path)?;
+        }
+        if !install_path.exists() {
+            let stored = store::store_package(staged.path(), ctx.verbose, None)?;
+            if let Err(e) = store::link_into(&stored.path, &install_path) {
+                let _ = fs::remove_dir_all(&install_path);
+                return Err(e);
+            }
+        }
+        FileManifest::compute(&install_path, HashFormat::of(&locked.checksum)?)?.save(env_path, name)?;
+
+        pb.suspend(|| {
+            if !ctx.quiet { println!("   + Installed {} (vendored)", name); }
+        });
+    }
+
+    pb.finish_and_clear();
+    Ok(())
+}
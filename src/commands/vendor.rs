@@ -0,0 +1,58 @@
+use crate::config::FinnConfig;
+use crate::commands::sync;
+use crate::lock::FinnLock;
+use crate::FinnContext;
+use crate::filelock;
+use crate::utils;
+use crate::vendor;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, anyhow, Context};
+use colored::*;
+
+pub fn run(dir: &str, ctx: &FinnContext) -> Result<()> {
+    // Relative to where the command was run; FinnConfig::load moves to the project root
+    let target = std::env::current_dir()?.join(dir);
+
+    let _env_lock = filelock::project_env()?;
+    let config = FinnConfig::load()?;
+    let root = std::env::current_dir()?;
+
+    // Always vendor from the real sources, even if [vendor] is already configured
+    sync::install_locked(&config, ctx)?;
+
+    let lock = FinnLock::load()?;
+    let packages_dir = Path::new(&config.project.envpath).join("packages");
+    fs::create_dir_all(&target).with_context(|| format!("Failed to create {:?}", target))?;
+
+    let pb = utils::create_spinner("Vendoring dependencies...", ctx.quiet);
+    let names = lock.reachable(config.packages.iter().flat_map(|p| p.keys()));
+
+    for name in &names {
+        let installed = packages_dir.join(name);
+        if !installed.is_dir() {
+            pb.finish_and_clear();
+            return Err(anyhow!("Package '{}' is in finn.lock but not installed. Run 'finn sync' first.", name));
+        }
+        vendor::vendor_package(&installed, &target.join(name), &lock.packages[name].checksum)?;
+        if ctx.verbose { pb.suspend(|| println!("   Vendored {}", name)); }
+    }
+
+    // Drop packages that are no longer depended on; only touch directories finn wrote
+    for entry in fs::read_dir(&target)?.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !names.contains(&name) && entry.path().join(vendor::CHECKSUM_FILE).exists() {
+            fs::remove_dir_all(entry.path())?;
+            if ctx.verbose { pb.suspend(|| println!("   Removed stale {}", name)); }
+        }
+    }
+    pb.finish_and_clear();
+
+    if !ctx.quiet {
+        let shown = target.strip_prefix(&root).unwrap_or(&target).to_string_lossy().replace("\\", "/");
+        println!("{} Vendored {} packages into '{}'.", "[OK]".green(), names.len(), shown);
+        println!("To install from the vendored sources, add this to finn.toml:\n");
+        println!("[vendor]\ndirectory = \"{}\"", shown);
+    }
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::config::FinnConfig;
+use crate::lock::FinnLock;
+use crate::integrity::{FileManifest, HashFormat};
+use crate::FinnContext;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use colored::*;
+
+/// Checks installed packages against finn.lock and lists the files that changed.
+pub fn run(package: Option<String>, ctx: &FinnContext) -> Result<()> {
+    let config = FinnConfig::load()?;
+    let lock = FinnLock::load()?;
+    let env_path = Path::new(&config.project.envpath);
+    let packages_dir = env_path.join("packages");
+
+    let names = match package {
+        Some(name) if lock.packages.contains_key(&name) => vec![name],
+        Some(name) => return Err(anyhow!("Package '{}' is not in finn.lock", name)),
+        None => lock.reachable(config.packages.iter().flat_map(|p| p.keys())),
+    };
+
+    let mut problems = 0;
+    for name in &names {
+        let expected = &lock.packages[name].checksum;
+        let installed = packages_dir.join(name);
+        if !installed.is_dir() {
+            println!("{} {}: not installed (run 'finn sync')", "[FAIL]".red(), name);
+            problems += 1;
+            continue;
+        }
+
+        let current = FileManifest::compute(&installed, HashFormat::of(expected)?)?;
+        if current.checksum == *expected {
+            if ctx.verbose { println!("{} {}", "[OK]".green(), name); }
+            continue;
+        }
+
+        problems += 1;
+        match FileManifest::load(env_path, name).filter(|recorded| recorded.checksum == *expected) {
+            Some(recorded) => {
+                let diff = recorded.diff(&current);
+                println!("{} {}: {} files differ from finn.lock", "[FAIL]".red(), name, diff.len());
+                for line in diff.lines() {
+                    println!("{}", line);
+                }
+            }
+            None => {
+                println!("{} {}: contents do not match finn.lock (no file manifest was recorded for the locked version)", "[FAIL]".red(), name);
+                if ctx.verbose {
+                    println!("   Expected: {}\n   Actual:   {}", expected, current.checksum);
+                }
+            }
+        }
+    }
+
+    if problems > 0 {
+        return Err(anyhow!("{} packages failed verification. Run 'finn --force sync' to reinstall them.", problems));
+    }
+    if !ctx.quiet { println!("{} Verified {} packages.", "[OK]".green(), names.len()); }
+    Ok(())
+}
@@ -1,15 +1,52 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 use anyhow::{Context, Result, anyhow};
+use crate::integrity::{HashAlgorithm, HashFormat};
+use crate::store::LinkMode;
+use crate::utils;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FinnConfig {
     pub project: ProjectConfig,
     pub registry: Option<RegistryConfig>, 
-    pub packages: Option<HashMap<String, String>>,
+    pub packages: Option<HashMap<String, DependencySpec>>,
     pub scripts: Option<HashMap<String, String>>,
+    pub audit: Option<AuditConfig>,
+    pub licenses: Option<LicensesConfig>,
+    pub vendor: Option<VendorConfig>,
+}
+
+/// A `[packages]` entry: a source string such as `"user/repo@v1"`, or a table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DependencySpec {
+    Simple(String),
+    Detailed(DetailedDependency),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DetailedDependency {
+    /// Git URL, local path or registry name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// URL of a .tar.gz, .tar.xz or .zip archive (instead of `git`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Expected SHA-256 of the archive at `url`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Tag, branch or commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Directory of the package inside the repository, for monorepos
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subdir: Option<String>,
+    /// Check out git submodules, e.g. a vendored native library
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub submodules: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -17,12 +54,54 @@ pub struct RegistryConfig {
     pub url: String,
 }
 
+/// Settings for `finn audit`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AuditConfig {
+    /// Advisory database: a local directory or a git URL.
+    pub database: Option<String>,
+    /// Advisory IDs that should not fail the audit.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+}
+
+/// Settings for `finn licenses`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LicensesConfig {
+    /// SPDX identifiers that must not appear in the dependency tree.
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// Install dependencies from a checked-in directory written by `finn vendor`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VendorConfig {
+    /// Vendor directory, relative to the project root.
+    pub directory: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ProjectConfig {
     pub name: String,
     pub version: String,
     pub envpath: String,
     pub entrypoint: Option<String>,
+    /// One-line summary shown by the registry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// URL of the package's source repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    pub license: Option<String>,
+    #[serde(rename = "license-file")]
+    pub license_file: Option<String>,
+    /// Hash function for the checksums in finn.lock (default: sha256)
+    pub checksum: Option<HashAlgorithm>,
+    /// Files that make up the package, as gitignore patterns (default: everything)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Files left out of the package, in addition to those matched by `.finnignore`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
 }
 
 impl FinnConfig {
@@ -33,10 +112,20 @@ impl FinnConfig {
                 version: "0.1.0".to_string(),
                 envpath: ".finn".to_string(),
                 entrypoint: Some("main.fin".to_string()),
+                description: None,
+                repository: None,
+                license: None,
+                license_file: None,
+                checksum: None,
+                include: Vec::new(),
+                exclude: Vec::new(),
             },
             registry: None,
             packages: Some(HashMap::new()),
             scripts: Some(HashMap::new()),
+            audit: None,
+            licenses: None,
+            vendor: None,
         }
     }
 
@@ -55,7 +144,7 @@ impl FinnConfig {
     }
 
     /// Helper to walk up the directory tree
-    fn find_manifest(start: &Path) -> Option<std::path::PathBuf> {
+    pub fn find_manifest(start: &Path) -> Option<std::path::PathBuf> {
         let mut current = start;
         loop {
             let manifest = current.join("finn.toml");
@@ -80,9 +169,64 @@ impl FinnConfig {
         Ok(config)
     }
 
+    /// Format of the checksums written to finn.lock.
+    pub fn checksum_format(&self) -> HashFormat {
+        HashFormat::V2(self.project.checksum.unwrap_or_default())
+    }
+
     pub fn save(&self) -> Result<()> {
         let content = toml::to_string_pretty(self).context("Failed to serialize config")?;
         fs::write("finn.toml", content).context("Failed to write finn.toml")?;
         Ok(())
     }
 }
+
+/// User-wide settings from `~/.finn/config.toml`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct GlobalConfig {
+    #[serde(default)]
+    pub cache: CacheSettings,
+    #[serde(default)]
+    pub store: StoreSettings,
+    /// URL prefixes to fetch through a mirror instead, like git's `insteadOf`
+    #[serde(default)]
+    pub source_replace: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct CacheSettings {
+    /// Upper bound for `~/.finn/cache`, e.g. "2GB". Least recently used entries are evicted first.
+    pub max_size: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct StoreSettings {
+    /// How packages are placed from `~/.finn/store` into projects
+    #[serde(default)]
+    pub link_mode: LinkMode,
+}
+
+impl GlobalConfig {
+    pub fn load() -> Result<Self> {
+        let path = utils::get_home_dir()?.join(".finn").join("config.toml");
+        if !path.exists() {
+            return Ok(GlobalConfig::default());
+        }
+        let content = fs::read_to_string(&path).context("Failed to read ~/.finn/config.toml")?;
+        let config: GlobalConfig = toml::from_str(&content).context("Failed to parse ~/.finn/config.toml")?;
+        Ok(config)
+    }
+
+    /// The URL to actually fetch `url` from. The longest matching `[source-replace]` prefix wins;
+    /// callers keep recording the original URL so lockfiles stay portable.
+    pub fn replace_source(&self, url: &str) -> String {
+        self.source_replace.iter()
+            .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, replacement)| format!("{}{}", replacement, &url[prefix.len()..]))
+            .unwrap_or_else(|| url.to_string())
+    }
+}
@@ -0,0 +1,125 @@
+use crate::config::FinnConfig;
+use crate::store;
+use crate::utils;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow, Context};
+use colored::*;
+use indicatif::MultiProgress;
+
+/// Default time to wait for another finn process, overridable with `FINN_LOCK_TIMEOUT` (seconds).
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An exclusive advisory lock, released when dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+/// Takes an exclusive lock on `path`, waiting up to the timeout if another process holds it.
+/// `what` names the protected resource in the waiting message, which is printed around
+/// `progress` when there is one so that it does not tear up the progress display.
+pub fn acquire(path: &Path, what: &str, progress: Option<&MultiProgress>) -> Result<FileLock> {
+    let file = open(path)?;
+    match file.try_lock() {
+        Ok(()) => return Ok(FileLock { _file: file }),
+        Err(TryLockError::WouldBlock) => {}
+        Err(TryLockError::Error(e)) => return Err(anyhow!(e).context(format!("Failed to lock {:?}", path))),
+    }
+
+    let waiting = || eprintln!("{} Blocking: waiting for file lock on {}", "[INFO]".blue(), what);
+    match progress {
+        Some(multi) => multi.suspend(waiting),
+        None => waiting(),
+    }
+    let timeout = timeout();
+    let started = Instant::now();
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        match file.try_lock() {
+            Ok(()) => return Ok(FileLock { _file: file }),
+            Err(TryLockError::WouldBlock) if started.elapsed() < timeout => continue,
+            Err(TryLockError::WouldBlock) => {
+                return Err(anyhow!(
+                    "Timed out after {}s waiting for file lock on {} ({:?}). Is another finn process stuck?",
+                    timeout.as_secs(), what, path
+                ));
+            }
+            Err(TryLockError::Error(e)) => return Err(anyhow!(e).context(format!("Failed to lock {:?}", path))),
+        }
+    }
+}
+
+/// Like `acquire`, but returns `None` instead of waiting when the lock is held elsewhere.
+pub fn try_acquire(path: &Path) -> Result<Option<FileLock>> {
+    let file = open(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(FileLock { _file: file })),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(anyhow!(e).context(format!("Failed to lock {:?}", path))),
+    }
+}
+
+/// Lock guarding a single cache entry while it is populated or removed.
+pub fn cache_entry(key: &str, progress: Option<&MultiProgress>) -> Result<FileLock> {
+    acquire(&cache_entry_path(key)?, "package cache", progress)
+}
+
+pub fn try_cache_entry(key: &str) -> Result<Option<FileLock>> {
+    try_acquire(&cache_entry_path(key)?)
+}
+
+/// Lock guarding the creation of a package store entry, and its removal while it is linked from.
+/// Named like the entry itself,
+/// since checksums contain ':', which Windows does not allow in file names.
+pub fn store_entry(checksum: &str, progress: Option<&MultiProgress>) -> Result<FileLock> {
+    acquire(&locks_dir()?.join(format!("store-{}.lock", store::entry_name(checksum))), "package store", progress)
+}
+
+pub fn try_store_entry(checksum: &str) -> Result<Option<FileLock>> {
+    try_acquire(&locks_dir()?.join(format!("store-{}.lock", store::entry_name(checksum))))
+}
+
+/// Lock guarding read-modify-write cycles of the cache index.
+pub fn cache_index(progress: Option<&MultiProgress>) -> Result<FileLock> {
+    acquire(&locks_dir()?.join("index.lock"), "package cache", progress)
+}
+
+/// Lock guarding the project's environment directory, finn.toml and finn.lock.
+pub fn project_env() -> Result<FileLock> {
+    let config = FinnConfig::load()?;
+    let env_path = Path::new(&config.project.envpath);
+    acquire(&env_path.join(".lock"), "project environment", None)
+}
+
+/// Lock files live outside `cache/registry` so they never look like cache entries.
+fn locks_dir() -> Result<PathBuf> {
+    Ok(utils::get_home_dir()?.join(".finn").join("cache").join("locks"))
+}
+
+fn cache_entry_path(key: &str) -> Result<PathBuf> {
+    Ok(locks_dir()?.join(format!("{}.lock", key)))
+}
+
+fn open(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open lock file {:?}", path))
+}
+
+fn timeout() -> Duration {
+    let secs = std::env::var("FINN_LOCK_TIMEOUT").ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
@@ -0,0 +1,369 @@
+use git2::{
+    AutotagOption, CredentialType, Cred, Direction, ErrorClass, ErrorCode, FetchOptions,
+    ObjectType, Oid, ProxyOptions, RemoteCallbacks, Repository, SubmoduleUpdateOptions,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+/// Depth value that asks libgit2 to fetch the complete history of a shallow repository.
+const UNSHALLOW: i32 = i32::MAX;
+
+#[derive(Error, Debug)]
+pub enum GitError {
+    #[error("Authentication failed for '{url}'. For SSH, make sure your key is loaded in ssh-agent; for HTTPS, configure a git credential helper or token.")]
+    Auth { url: String },
+    #[error("Repository '{url}' was not found or is not accessible. Check the URL and your access rights.")]
+    RepoNotFound { url: String },
+    #[error("Reference '{reference}' was not found in '{url}'. Check the tag, branch or commit name.")]
+    RefNotFound { url: String, reference: String },
+    #[error("Network error while contacting '{url}': {message}. Check your connection, or use --offline if the package is already cached.")]
+    Network { url: String, message: String },
+    #[error("Git operation failed on '{url}': {message}")]
+    Other { url: String, message: String },
+}
+
+impl GitError {
+    fn classify(err: git2::Error, url: &str, reference: Option<&str>) -> Self {
+        let message = err.message().to_string();
+        let lower = message.to_lowercase();
+        let url = url.to_string();
+
+        if err.code() == ErrorCode::Auth || lower.contains("authentication") || lower.contains("401") || lower.contains("403") {
+            return GitError::Auth { url };
+        }
+        if let Some(r) = reference
+            && (err.code() == ErrorCode::NotFound || err.class() == ErrorClass::Reference || lower.contains("revspec"))
+        {
+            return GitError::RefNotFound { url, reference: r.to_string() };
+        }
+        if err.code() == ErrorCode::NotFound
+            || lower.contains("404")
+            || lower.contains("could not find repository")
+            || lower.contains("failed to resolve path")
+            || lower.contains("does not appear to be a git repository")
+        {
+            return GitError::RepoNotFound { url };
+        }
+        match err.class() {
+            ErrorClass::Net | ErrorClass::Http | ErrorClass::Ssh | ErrorClass::Ssl | ErrorClass::Os => {
+                GitError::Network { url, message }
+            }
+            _ => GitError::Other { url, message },
+        }
+    }
+}
+
+pub type GitResult<T> = std::result::Result<T, GitError>;
+
+/// Creates an empty repository at `path` with `url` as its `origin` remote.
+pub fn init_mirror(path: &Path, url: &str) -> GitResult<()> {
+    let repo = Repository::init(path).map_err(|e| GitError::classify(e, url, None))?;
+    repo.remote("origin", url).map_err(|e| GitError::classify(e, url, None))?;
+    Ok(())
+}
+
+/// Points `origin` at `url`, e.g. after a source mirror was configured or changed.
+pub fn set_origin(path: &Path, url: &str) -> GitResult<()> {
+    let repo = open(path)?;
+    if origin_url(&repo) != url {
+        repo.remote_set_url("origin", url).map_err(|e| GitError::classify(e, url, None))?;
+    }
+    Ok(())
+}
+
+/// Fetches `target` (a branch, tag or commit; `None` for the remote's default branch) from `origin`.
+/// With `shallow`, only that ref is fetched at depth 1; when the transport or server cannot do that
+/// (local paths, abbreviated commits), the full history is fetched instead.
+pub fn fetch(repo_path: &Path, target: Option<&str>, shallow: bool, verbose: bool) -> GitResult<()> {
+    let repo = open(repo_path)?;
+    let url = origin_url(&repo);
+
+    let advertised = list_remote(&repo, &url)?;
+    if shallow {
+        match fetch_shallow(&repo, &url, target, &advertised) {
+            Ok(()) => return Ok(()),
+            Err(e) => if verbose { println!("   Shallow fetch not possible ({}), fetching full history...", e); },
+        }
+    }
+
+    let refspecs = ["+refs/heads/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"];
+    let mut options = fetch_options();
+    options.prune(git2::FetchPrune::On).download_tags(AutotagOption::All);
+    if repo.is_shallow() {
+        options.depth(UNSHALLOW);
+    }
+    let mut remote = repo.find_remote("origin").map_err(|e| GitError::classify(e, &url, None))?;
+    remote.fetch(&refspecs, Some(&mut options), None).map_err(|e| GitError::classify(e, &url, None))?;
+
+    // Follow upstream default branch changes
+    if let Some(branch) = advertised.default_branch.as_deref().and_then(|b| b.strip_prefix("refs/heads/")) {
+        let _ = repo.reference_symbolic("refs/remotes/origin/HEAD", &format!("refs/remotes/origin/{}", branch), true, "finn: set origin/HEAD");
+    }
+    Ok(())
+}
+
+/// What `origin` advertises.
+struct Advertised {
+    refs: Vec<(String, Oid)>,
+    default_branch: Option<String>,
+}
+
+fn list_remote(repo: &Repository, url: &str) -> GitResult<Advertised> {
+    let mut remote = repo.find_remote("origin").map_err(|e| GitError::classify(e, url, None))?;
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(callbacks()), Some(proxy_options()))
+        .map_err(|e| GitError::classify(e, url, None))?;
+    let refs = connection.list().map_err(|e| GitError::classify(e, url, None))?
+        .iter()
+        .map(|head| (head.name().to_string(), head.oid()))
+        .collect();
+    let default_branch = connection.default_branch().ok()
+        .and_then(|b| b.as_str().map(|s| s.to_string()));
+    Ok(Advertised { refs, default_branch })
+}
+
+fn fetch_shallow(repo: &Repository, url: &str, target: Option<&str>, advertised: &Advertised) -> GitResult<()> {
+    let has = |name: &str| advertised.refs.iter().any(|(n, _)| n == name);
+    let not_found = |reference: &str| GitError::RefNotFound { url: url.to_string(), reference: reference.to_string() };
+
+    let refspec = match target {
+        None => {
+            let branch = advertised.default_branch.as_deref().ok_or_else(|| not_found("HEAD"))?;
+            let short = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+            format!("+{}:refs/remotes/origin/{}", branch, short)
+        }
+        Some(r) if has(&format!("refs/heads/{}", r)) => format!("+refs/heads/{0}:refs/remotes/origin/{0}", r),
+        Some(r) if has(&format!("refs/tags/{}", r)) => format!("+refs/tags/{0}:refs/tags/{0}", r),
+        Some(r) if r.len() == 40 && r.chars().all(|c| c.is_ascii_hexdigit()) => {
+            format!("+{}:refs/finn/pinned", r)
+        }
+        Some(r) => return Err(not_found(r)),
+    };
+
+    let mut options = fetch_options();
+    options.depth(1).download_tags(AutotagOption::None);
+    let mut remote = repo.find_remote("origin").map_err(|e| GitError::classify(e, url, None))?;
+    remote.fetch(&[refspec.as_str()], Some(&mut options), None)
+        .map_err(|e| GitError::classify(e, url, target))?;
+
+    if target.is_none()
+        && let Some(branch) = advertised.default_branch.as_deref().and_then(|b| b.strip_prefix("refs/heads/"))
+    {
+        let _ = repo.reference_symbolic("refs/remotes/origin/HEAD", &format!("refs/remotes/origin/{}", branch), true, "finn: set origin/HEAD");
+    }
+    Ok(())
+}
+
+/// Detaches the working tree at `target`, preferring the remote-tracking branch of the same name
+/// so that fetched branch updates are picked up.
+pub fn checkout(repo_path: &Path, target: Option<&str>) -> GitResult<()> {
+    let repo = open(repo_path)?;
+    let url = origin_url(&repo);
+    let reference = target.unwrap_or("HEAD");
+
+    let spec = match target {
+        None if rev_exists(repo_path, "refs/remotes/origin/HEAD") => "refs/remotes/origin/HEAD".to_string(),
+        None => "HEAD".to_string(),
+        Some(r) if rev_exists(repo_path, &format!("refs/remotes/origin/{}", r)) => format!("refs/remotes/origin/{}", r),
+        Some(r) => r.to_string(),
+    };
+
+    let commit = repo.revparse_single(&spec)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|e| GitError::classify(e, &url, Some(reference)))?;
+
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force();
+    repo.checkout_tree(commit.as_object(), Some(&mut checkout))
+        .and_then(|_| repo.set_head_detached(commit.id()))
+        .map_err(|e| GitError::classify(e, &url, Some(reference)))?;
+    Ok(())
+}
+
+pub fn rev_exists(repo_path: &Path, rev: &str) -> bool {
+    Repository::open(repo_path)
+        .and_then(|repo| repo.revparse_single(rev).map(|_| ()))
+        .is_ok()
+}
+
+/// Commits and tags never move, so if one is already present there is nothing to fetch.
+pub fn is_immutable_ref_present(repo_path: &Path, reference: &str) -> bool {
+    let Ok(repo) = Repository::open(repo_path) else { return false };
+    let is_commit_id = (7..=40).contains(&reference.len()) && reference.chars().all(|c| c.is_ascii_hexdigit());
+    if is_commit_id && repo.revparse_single(reference).and_then(|o| o.peel(ObjectType::Commit)).is_ok() {
+        return true;
+    }
+    repo.find_reference(&format!("refs/tags/{}", reference)).is_ok()
+}
+
+/// Current `HEAD` commit of a git checkout, if it is one.
+pub fn head_commit(path: &Path) -> Option<String> {
+    // Only look at real checkouts, not directories nested inside some other repository
+    let repo = Repository::open(path).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+pub fn is_shallow(path: &Path) -> bool {
+    Repository::open(path).map(|r| r.is_shallow()).unwrap_or(false)
+}
+
+/// Initializes and checks out all submodules, recursively. `resolve_url` is called with the path
+/// and URL of every submodule declared with an absolute URL and returns the URL to fetch it from;
+/// it is written to the local config only, so `.gitmodules` stays as committed. Relative URLs
+/// resolve against the parent's `origin`, which has already been resolved.
+pub fn update_submodules<E: From<GitError>>(
+    repo_path: &Path,
+    offline: bool,
+    resolve_url: &dyn Fn(&str, &str) -> Result<String, E>,
+) -> Result<(), E> {
+    let repo = open(repo_path)?;
+    let url = origin_url(&repo);
+    for mut submodule in repo.submodules().map_err(|e| GitError::classify(e, &url, None))? {
+        let name = submodule.name().unwrap_or_default().to_string();
+        let path = submodule.path().to_string_lossy().to_string();
+        let declared = submodule.url().unwrap_or_default().to_string();
+        let sub_url = if declared.starts_with("./") || declared.starts_with("../") {
+            declared
+        } else {
+            resolve_url(&path, &declared)?
+        };
+
+        submodule.init(false).map_err(|e| GitError::classify(e, &sub_url, None))?;
+        repo.config()
+            .and_then(|mut config| config.set_str(&format!("submodule.{}.url", name), &sub_url))
+            .map_err(|e| GitError::classify(e, &sub_url, None))?;
+        // An existing checkout keeps fetching from the remote it was cloned from
+        if let Ok(sub_repo) = submodule.open()
+            && sub_repo.find_remote("origin").is_ok()
+        {
+            set_origin(sub_repo.workdir().unwrap_or(sub_repo.path()), &sub_url)?;
+        }
+
+        // Looked up again so that the update sees the URL from the local config
+        let mut submodule = repo.find_submodule(&name).map_err(|e| GitError::classify(e, &sub_url, None))?;
+        let mut options = SubmoduleUpdateOptions::new();
+        options.fetch(fetch_options()).allow_fetch(!offline);
+        submodule.update(true, Some(&mut options))
+            .map_err(|e| GitError::classify(e, &sub_url, None))?;
+        update_submodules(&repo_path.join(submodule.path()), offline, resolve_url)?;
+    }
+    Ok(())
+}
+
+/// Removes checked-out submodule files so that they do not end up in installed packages.
+pub fn deinit_submodules(repo_path: &Path) -> GitResult<()> {
+    let repo = open(repo_path)?;
+    let url = origin_url(&repo);
+    for submodule in repo.submodules().map_err(|e| GitError::classify(e, &url, None))? {
+        let workdir = repo_path.join(submodule.path());
+        if workdir.join(".git").exists() {
+            let _ = fs::remove_dir_all(&workdir);
+            let _ = fs::create_dir_all(&workdir);
+            let _ = fs::remove_dir_all(repo.path().join("modules").join(submodule.name().unwrap_or_default()));
+        }
+    }
+    Ok(())
+}
+
+/// Checked-out submodule commits below `within` (a path relative to the checkout, or ""),
+/// keyed by their path relative to `within`.
+pub fn submodule_commits(repo_path: &Path, within: &str) -> GitResult<BTreeMap<String, String>> {
+    let mut all = BTreeMap::new();
+    collect_submodules(repo_path, "", &mut all)?;
+
+    let prefix = within.trim_end_matches('/');
+    Ok(all.into_iter()
+        .filter_map(|(path, commit)| {
+            let relative = if prefix.is_empty() {
+                Some(path.as_str())
+            } else {
+                path.strip_prefix(prefix).and_then(|p| p.strip_prefix('/'))
+            };
+            relative.map(|r| (r.to_string(), commit.clone()))
+        })
+        .collect())
+}
+
+fn collect_submodules(repo_path: &Path, prefix: &str, out: &mut BTreeMap<String, String>) -> GitResult<()> {
+    if !repo_path.join(".gitmodules").exists() {
+        return Ok(());
+    }
+    let repo = open(repo_path)?;
+    let url = origin_url(&repo);
+    for submodule in repo.submodules().map_err(|e| GitError::classify(e, &url, None))? {
+        // Submodules that are not checked out have no working tree commit
+        let Some(commit) = submodule.workdir_id() else { continue };
+        let path = format!("{}{}", prefix, submodule.path().to_string_lossy().replace("\\", "/"));
+        out.insert(path.clone(), commit.to_string());
+        collect_submodules(&repo_path.join(submodule.path()), &format!("{}/", path), out)?;
+    }
+    Ok(())
+}
+
+/// Creates a repository for a new project and commits its files, if an identity is configured.
+/// Returns whether the initial commit was made.
+pub fn init_project(root: &Path) -> GitResult<bool> {
+    let url = root.to_string_lossy().to_string();
+    let wrap = |e| GitError::classify(e, &url, None);
+
+    let repo = Repository::init(root).map_err(wrap)?;
+    let Ok(signature) = repo.signature() else { return Ok(false) };
+
+    let mut index = repo.index().map_err(wrap)?;
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).map_err(wrap)?;
+    index.write().map_err(wrap)?;
+    let tree = repo.find_tree(index.write_tree().map_err(wrap)?).map_err(wrap)?;
+    repo.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[]).map_err(wrap)?;
+    Ok(true)
+}
+
+fn open(path: &Path) -> GitResult<Repository> {
+    Repository::open(path).map_err(|e| GitError::classify(e, &path.to_string_lossy(), None))
+}
+
+fn origin_url(repo: &Repository) -> String {
+    repo.find_remote("origin").ok()
+        .and_then(|r| r.url().map(|u| u.to_string()))
+        .unwrap_or_else(|| repo.path().to_string_lossy().to_string())
+}
+
+fn fetch_options<'a>() -> FetchOptions<'a> {
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks()).proxy_options(proxy_options());
+    options
+}
+
+fn proxy_options<'a>() -> ProxyOptions<'a> {
+    let mut proxy = ProxyOptions::new();
+    proxy.auto();
+    proxy
+}
+
+/// Tries ssh-agent for SSH remotes and git credential helpers for HTTPS, giving up after a few
+/// rounds so that bad credentials fail instead of looping.
+fn callbacks<'a>() -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut attempts = 0;
+    callbacks.credentials(move |url, username, allowed| {
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::new(ErrorCode::Auth, ErrorClass::Net, "authentication failed"));
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT)
+            && let Ok(config) = git2::Config::open_default()
+        {
+            return Cred::credential_helper(&config, url, username);
+        }
+        if allowed.contains(CredentialType::DEFAULT) {
+            return Cred::default();
+        }
+        Err(git2::Error::new(ErrorCode::Auth, ErrorClass::Net, "no supported credentials"))
+    });
+    callbacks
+}
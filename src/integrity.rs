@@ -1,45 +1,380 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
-use anyhow::{Result, Context};
+use std::path::{Component, Path, PathBuf};
+use anyhow::{Result, anyhow, Context};
 use sha2::{Sha256, Digest};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use walkdir::WalkDir;
 
+/// Hash function for new checksums, set with `checksum = "blake3"` under `[project]`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+/// How a checksum was computed, as read from its prefix (`sha256-v2:<hex>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashFormat {
+    /// Unprefixed SHA-256 over path and content, written by earlier versions of finn.
+    /// Still verified so older lockfiles keep working; `finn sync` rewrites them.
+    Legacy,
+    /// Length-prefixed path, file kind and content. Symlinks are hashed as links, not followed.
+    /// The executable bit is left out: Windows cannot represent it, and a lockfile has to
+    /// verify on every platform.
+    V2(HashAlgorithm),
+}
+
+impl HashFormat {
+    pub fn of(checksum: &str) -> Result<Self> {
+        match checksum.split_once(':') {
+            None => Ok(HashFormat::Legacy),
+            Some(("sha256-v2", _)) => Ok(HashFormat::V2(HashAlgorithm::Sha256)),
+            Some(("blake3-v2", _)) => Ok(HashFormat::V2(HashAlgorithm::Blake3)),
+            Some((prefix, _)) => Err(anyhow!(
+                "Unsupported checksum format '{}'. The lockfile was probably written by a newer finn.", prefix
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashFormat::Legacy => "legacy sha256",
+            HashFormat::V2(HashAlgorithm::Sha256) => "sha256-v2",
+            HashFormat::V2(HashAlgorithm::Blake3) => "blake3-v2",
+        }
+    }
+
+    fn hasher(self) -> Hasher {
+        match self {
+            HashFormat::Legacy | HashFormat::V2(HashAlgorithm::Sha256) => Hasher::Sha256(Sha256::new()),
+            HashFormat::V2(HashAlgorithm::Blake3) => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+}
+
+/// The hex digest and its algorithm, e.g. for SBOM hashes. `None` for malformed checksums.
+pub fn digest_parts(checksum: &str) -> Option<(HashAlgorithm, &str)> {
+    let algorithm = match HashFormat::of(checksum).ok()? {
+        HashFormat::Legacy => HashAlgorithm::Sha256,
+        HashFormat::V2(algorithm) => algorithm,
+    };
+    let digest = checksum.rsplit(':').next()?;
+    (digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit())).then_some((algorithm, digest))
+}
+
+/// Package checksum of `root` in the current format (`sha256-v2`).
 pub fn calculate_package_hash(root: &Path) -> Result<String> {
-    let mut hasher = Sha256::new();
-    
-    // Collect all entries
+    package_hash(root, HashFormat::V2(HashAlgorithm::default()))
+}
+
+pub fn package_hash(root: &Path, format: HashFormat) -> Result<String> {
+    hash_tree(root, format, None)
+}
+
+/// Whether the files below `root` add up to `expected`, in whatever format `expected` was written.
+pub fn matches(root: &Path, expected: &str) -> Result<bool> {
+    Ok(package_hash(root, HashFormat::of(expected)?)? == expected)
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(bytes),
+            Hasher::Blake3(h) => { h.update(bytes); }
+        }
+    }
+
+    /// Length-prefixed, so ("a", "bc") and ("ab", "c") hash differently.
+    fn update_framed(&mut self, bytes: &[u8]) {
+        self.update(&(bytes.len() as u64).to_le_bytes());
+        self.update(bytes);
+    }
+
+    fn finalize(self) -> String {
+        match self {
+            Hasher::Sha256(h) => hex::encode(h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// A file as it contributes to a `V2` hash.
+enum Entry {
+    File(Vec<u8>),
+    /// Target of a symlink, with forward slashes
+    Symlink(String),
+}
+
+impl Entry {
+    fn read(path: &Path) -> Result<Self> {
+        let metadata = fs::symlink_metadata(path).with_context(|| format!("Failed to read {:?}", path))?;
+        if metadata.file_type().is_symlink() {
+            let target = fs::read_link(path).with_context(|| format!("Failed to read link {:?}", path))?;
+            return Ok(Entry::Symlink(target.to_string_lossy().replace("\\", "/")));
+        }
+        Ok(Entry::File(fs::read(path).with_context(|| format!("Failed to read {:?}", path))?))
+    }
+
+    fn hash_into(&self, hasher: &mut Hasher) {
+        let (kind, content): (u8, &[u8]) = match self {
+            Entry::File(bytes) => (b'f', bytes),
+            Entry::Symlink(target) => (b'l', target.as_bytes()),
+        };
+        hasher.update(&[kind]);
+        hasher.update_framed(content);
+    }
+}
+
+#[cfg(unix)]
+pub fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Digest of a single file as recorded in a `FileManifest` of the given format.
+pub fn file_digest(path: &Path, format: HashFormat) -> Result<String> {
+    let mut hasher = format.hasher();
+    match format {
+        HashFormat::Legacy => hasher.update(&fs::read(path).with_context(|| format!("Failed to read {:?}", path))?),
+        HashFormat::V2(_) => Entry::read(path)?.hash_into(&mut hasher),
+    }
+    Ok(hasher.finalize())
+}
+
+/// Ignore rules of a package, in gitignore syntax, next to its finn.toml.
+pub const IGNORE_FILE: &str = ".finnignore";
+
+/// The parts of a package's finn.toml that decide which files belong to it.
+/// Read leniently, since any directory can be hashed, not just valid packages.
+#[derive(Deserialize, Default)]
+struct ManifestRules {
+    #[serde(default)]
+    project: FileRules,
+}
+
+#[derive(Deserialize, Default)]
+struct FileRules {
+    envpath: Option<String>,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+/// Decides which files below a package root are part of the package. Left out are `.git`,
+/// the package's own environment directory (`.finn`), anything matched by `.finnignore` or
+/// `exclude`, and, if `include` is set, everything it does not match. finn.toml and
+/// `.finnignore` are always kept, so a copy of the package selects the same files.
+pub struct PackageFilter {
+    ignore: Gitignore,
+    include: Option<Gitignore>,
+}
+
+impl PackageFilter {
+    pub fn load(root: &Path) -> Result<Self> {
+        let rules = fs::read_to_string(root.join("finn.toml")).ok()
+            .and_then(|content| toml::from_str::<ManifestRules>(&content).ok())
+            .unwrap_or_default()
+            .project;
+
+        let mut ignore = GitignoreBuilder::new(root);
+        ignore.add_line(None, ".git")?;
+        let envpath = rules.envpath.as_deref().unwrap_or(".finn");
+        if Path::new(envpath).components().all(|c| matches!(c, Component::Normal(_))) {
+            ignore.add_line(None, &format!("/{}/", envpath.replace("\\", "/").trim_end_matches('/')))?;
+        }
+        let ignore_file = root.join(IGNORE_FILE);
+        if ignore_file.is_file()
+            && let Some(e) = ignore.add(&ignore_file)
+        {
+            return Err(anyhow!("Invalid pattern in {:?}: {}", ignore_file, e));
+        }
+        for pattern in &rules.exclude {
+            ignore.add_line(None, pattern).with_context(|| format!("Invalid exclude pattern '{}' in finn.toml", pattern))?;
+        }
+
+        let include = if rules.include.is_empty() {
+            None
+        } else {
+            let mut include = GitignoreBuilder::new(root);
+            for pattern in &rules.include {
+                include.add_line(None, pattern).with_context(|| format!("Invalid include pattern '{}' in finn.toml", pattern))?;
+            }
+            Some(include.build()?)
+        };
+
+        Ok(PackageFilter { ignore: ignore.build()?, include })
+    }
+
+    /// Whether `relative` (a path below the package root) is part of the package.
+    pub fn includes(&self, relative: &Path, is_dir: bool) -> bool {
+        if relative == Path::new("finn.toml") || relative == Path::new(IGNORE_FILE) {
+            return true;
+        }
+        if self.ignore.matched_path_or_any_parents(relative, is_dir).is_ignore() {
+            return false;
+        }
+        // `include` selects files; directories are walked to find them
+        is_dir || self.include.as_ref().is_none_or(|i| i.matched_path_or_any_parents(relative, false).is_ignore())
+    }
+}
+
+/// Files, symlinks included, that make up a package, in hashing order,
+/// as (normalized relative path, full path).
+pub fn package_files(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let filter = PackageFilter::load(root)?;
     let mut entries: Vec<_> = WalkDir::new(root)
         .into_iter()
+        .filter_entry(|e| e.depth() == 0 || filter.includes(e.path().strip_prefix(root).unwrap_or(e.path()), e.file_type().is_dir()))
         .filter_map(|e| e.ok())
+        .filter(|e| !e.file_type().is_dir())
         .collect();
 
     // Sort by path to ensure deterministic hash regardless of OS/File System order
     entries.sort_by_key(|e| e.path().to_path_buf());
+    Ok(entries.into_iter().map(|e| (relative_path(root, e.path()), e.path().to_path_buf())).collect())
+}
 
-    for entry in entries {
-        let path = entry.path();
-        
-        if path.is_dir() {
-            continue;
+/// Files as `HashFormat::Legacy` saw them: everything except `.git`, with no ignore rules.
+fn legacy_files(root: &Path) -> Vec<(String, PathBuf)> {
+    let mut entries: Vec<_> = WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .collect();
+    entries.sort_by_key(|e| e.path().to_path_buf());
+    entries.into_iter()
+        .filter(|e| !e.file_type().is_dir() && !e.path().components().any(|c| c.as_os_str() == ".git"))
+        .map(|e| (relative_path(root, e.path()), e.path().to_path_buf()))
+        .collect()
+}
+
+/// Path below `root` with forward slashes, so C:\Lib and /tmp/Lib produce the same hash.
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace("\\", "/")
+}
+
+/// Hashes the package at `root`, recording a digest per file into `files` if given.
+fn hash_tree(root: &Path, format: HashFormat, mut files: Option<&mut BTreeMap<String, String>>) -> Result<String> {
+    let mut hasher = format.hasher();
+    let entries = match format {
+        HashFormat::Legacy => legacy_files(root),
+        HashFormat::V2(_) => package_files(root)?,
+    };
+    for (relative_path, path) in entries {
+        let mut file_hasher = format.hasher();
+        match format {
+            HashFormat::Legacy => {
+                // Symlinks were followed, and links to directories skipped
+                if path.is_dir() {
+                    continue;
+                }
+                let bytes = fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+                hasher.update(relative_path.as_bytes());
+                hasher.update(&bytes);
+                if files.is_some() { file_hasher.update(&bytes); }
+            }
+            HashFormat::V2(_) => {
+                let entry = Entry::read(&path)?;
+                hasher.update_framed(relative_path.as_bytes());
+                entry.hash_into(&mut hasher);
+                if files.is_some() { entry.hash_into(&mut file_hasher); }
+            }
+        }
+        if let Some(files) = files.as_deref_mut() {
+            files.insert(relative_path, file_hasher.finalize());
+        }
+    }
+    Ok(match format {
+        HashFormat::Legacy => hasher.finalize(),
+        HashFormat::V2(_) => format!("{}:{}", format.name(), hasher.finalize()),
+    })
+}
+
+/// Per-file digests of an installed package, so a checksum mismatch can name the files.
+/// Stored as `<envpath>/manifests/<package>.json`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct FileManifest {
+    /// Package hash the files add up to (as in finn.lock)
+    pub checksum: String,
+    pub files: BTreeMap<String, String>,
+}
+
+/// How a package's files differ from a recorded manifest.
+#[derive(Debug, Default)]
+pub struct FileDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl FileManifest {
+    /// Hashes every file below `root` in one pass, computing the package hash along the way.
+    pub fn compute(root: &Path, format: HashFormat) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        let checksum = hash_tree(root, format, Some(&mut files))?;
+        Ok(FileManifest { checksum, files })
+    }
+
+    pub fn path(env_path: &Path, name: &str) -> PathBuf {
+        env_path.join("manifests").join(format!("{}.json", name))
+    }
+
+    /// The recorded manifest, if there is a readable one.
+    pub fn load(env_path: &Path, name: &str) -> Option<Self> {
+        let content = fs::read_to_string(Self::path(env_path, name)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, env_path: &Path, name: &str) -> Result<()> {
+        let path = Self::path(env_path, name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
         }
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write file manifest {:?}", path))
+    }
 
-        // Skip .git directory to avoid hashing metadata that changes
-        if path.components().any(|c| c.as_os_str() == ".git") {
-            continue;
+    /// Changes needed to get from this (recorded) manifest to `current`.
+    pub fn diff(&self, current: &FileManifest) -> FileDiff {
+        let mut diff = FileDiff::default();
+        for (file, digest) in &current.files {
+            match self.files.get(file) {
+                None => diff.added.push(file.clone()),
+                Some(recorded) if recorded != digest => diff.modified.push(file.clone()),
+                Some(_) => {}
+            }
         }
+        diff.removed = self.files.keys().filter(|f| !current.files.contains_key(*f)).cloned().collect();
+        diff
+    }
+}
 
-        // Hash the relative path (so C:\Lib and /tmp/Lib produce same hash)
-        let relative_path = path.strip_prefix(root)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .replace("\\", "/"); // Normalize separators
-        
-        hasher.update(relative_path.as_bytes());
-        
-        // Hash the file content
-        let bytes = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
-        hasher.update(&bytes);
+impl FileDiff {
+    /// Number of files that differ.
+    pub fn len(&self) -> usize {
+        self.added.len() + self.removed.len() + self.modified.len()
     }
 
-    Ok(hex::encode(hasher.finalize()))
+    /// One line per file, e.g. `   modified: src/lib.fin`.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (label, files) in [("modified", &self.modified), ("added", &self.added), ("removed", &self.removed)] {
+            lines.extend(files.iter().map(|f| format!("   {}: {}", label, f)));
+        }
+        lines
+    }
 }
@@ -0,0 +1,131 @@
+use crate::config::FinnConfig;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where a package's license information came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LicenseOrigin {
+    /// `license` in the package's finn.toml
+    Manifest,
+    /// Recognized from the text of a license file
+    Detected,
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct PackageLicense {
+    /// SPDX expression, if known
+    pub expression: Option<String>,
+    pub origin: LicenseOrigin,
+    pub files: Vec<PathBuf>,
+}
+
+/// Determines the license of a package rooted at `root`.
+/// The manifest `license` field wins; otherwise the license file text is matched against known licenses.
+pub fn detect_package_license(root: &Path, config: Option<&FinnConfig>) -> PackageLicense {
+    let mut files = find_license_files(root);
+
+    // An explicit license-file is listed first even if it has an unusual name
+    if let Some(explicit) = config.and_then(|c| c.project.license_file.as_ref()) {
+        let path = root.join(explicit);
+        if path.is_file() && !files.contains(&path) {
+            files.insert(0, path);
+        }
+    }
+
+    if let Some(expr) = config.and_then(|c| c.project.license.clone()).filter(|l| !l.trim().is_empty()) {
+        return PackageLicense { expression: Some(expr), origin: LicenseOrigin::Manifest, files };
+    }
+
+    let mut detected: Vec<&'static str> = Vec::new();
+    for file in &files {
+        if let Ok(text) = fs::read_to_string(file)
+            && let Some(id) = identify_license_text(&text)
+            && !detected.contains(&id)
+        {
+            detected.push(id);
+        }
+    }
+
+    if detected.is_empty() {
+        return PackageLicense { expression: None, origin: LicenseOrigin::Unknown, files };
+    }
+
+    // Several license files (e.g. LICENSE-MIT + LICENSE-APACHE) usually mean a dual license
+    PackageLicense {
+        expression: Some(detected.join(" OR ")),
+        origin: LicenseOrigin::Detected,
+        files,
+    }
+}
+
+/// Top-level files such as LICENSE, LICENSE-MIT, LICENCE.md or COPYING.
+pub fn find_license_files(root: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(root) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+            .map(|e| e.path())
+            .filter(|p| {
+                let name = p.file_name().unwrap_or_default().to_string_lossy().to_uppercase();
+                ["LICENSE", "LICENCE", "COPYING", "UNLICENSE"].iter().any(|prefix| name.starts_with(prefix))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+/// Recognizes common license texts and returns their SPDX identifier.
+pub fn identify_license_text(text: &str) -> Option<&'static str> {
+    let t = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+
+    if t.contains("apache license") && t.contains("version 2.0") {
+        return Some("Apache-2.0");
+    }
+    if t.contains("mozilla public license") && t.contains("2.0") {
+        return Some("MPL-2.0");
+    }
+    if t.contains("gnu lesser general public license") {
+        return Some(if t.contains("version 3") { "LGPL-3.0" } else { "LGPL-2.1" });
+    }
+    if t.contains("gnu affero general public license") {
+        return Some("AGPL-3.0");
+    }
+    if t.contains("gnu general public license") {
+        return Some(if t.contains("version 3") { "GPL-3.0" } else { "GPL-2.0" });
+    }
+    if t.contains("boost software license") {
+        return Some("BSL-1.0");
+    }
+    if t.contains("free and unencumbered software released into the public domain") {
+        return Some("Unlicense");
+    }
+    if t.contains("permission is hereby granted, free of charge") {
+        return Some("MIT");
+    }
+    if t.contains("permission to use, copy, modify, and/or distribute this software") {
+        return Some("ISC");
+    }
+    if t.contains("redistribution and use in source and binary forms") {
+        return Some(if t.contains("neither the name") { "BSD-3-Clause" } else { "BSD-2-Clause" });
+    }
+    if t.contains("this software is provided 'as-is'") && t.contains("altered source versions must be plainly marked") {
+        return Some("Zlib");
+    }
+    None
+}
+
+/// Evaluates a simple SPDX expression against `allowed`:
+/// every `AND` term of some `OR` branch must be accepted.
+pub fn expression_satisfies(expression: &str, allowed: impl Fn(&str) -> bool) -> bool {
+    let cleaned = expression.replace(['(', ')'], " ");
+    cleaned.split(" OR ").any(|branch| {
+        branch.split(" AND ").all(|id| {
+            let id = id.trim();
+            !id.is_empty() && allowed(id)
+        })
+    })
+}
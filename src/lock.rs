@@ -1,12 +1,14 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::Path;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use crate::validator::validate_package_name;
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct FinnLock {
-    pub packages: HashMap<String, LockedPackage>,
+    /// Sorted, so the file is byte-for-byte stable for the same dependency graph
+    pub packages: BTreeMap<String, LockedPackage>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -16,6 +18,18 @@ pub struct LockedPackage {
     pub commit: String,
     #[serde(default)] // Allow old lockfiles to load without crashing
     pub checksum: String, 
+    /// Names of the packages this package depends on directly
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    /// Package directory inside the source repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subdir: Option<String>,
+    /// SHA-256 of the downloaded file, for archive sources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Checked-out submodule commits, keyed by path inside the package
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub submodules: BTreeMap<String, String>,
 }
 
 impl FinnLock {
@@ -25,6 +39,13 @@ impl FinnLock {
         }
         let content = fs::read_to_string("finn.lock")?;
         let lock: FinnLock = toml::from_str(&content)?;
+
+        // Names become directories under .finn/packages, the cache and vendor directories
+        for (name, package) in &lock.packages {
+            for name in std::iter::once(name).chain(&package.dependencies) {
+                validate_package_name(name).context("finn.lock contains an invalid package name")?;
+            }
+        }
         Ok(lock)
     }
 
@@ -34,12 +55,22 @@ impl FinnLock {
         Ok(())
     }
 
-    pub fn update(&mut self, name: String, source: String, commit: String, version: String, checksum: String) {
-        self.packages.insert(name, LockedPackage {
-            source,
-            commit,
-            version,
-            checksum,
-        });
+    pub fn update(&mut self, name: String, package: LockedPackage) {
+        self.packages.insert(name, package);
+    }
+
+    /// Locked packages reachable from `roots` through their dependencies, sorted by name.
+    /// Entries left behind by removed packages are not included.
+    pub fn reachable<'a>(&self, roots: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+        let mut seen = BTreeSet::new();
+        let mut pending: Vec<String> = roots.into_iter().cloned().collect();
+        while let Some(name) = pending.pop() {
+            if let Some(package) = self.packages.get(&name)
+                && seen.insert(name)
+            {
+                pending.extend(package.dependencies.iter().cloned());
+            }
+        }
+        seen.into_iter().collect()
     }
 }
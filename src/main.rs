@@ -3,8 +3,19 @@ mod utils;
 mod lock;
 mod validator;
 mod cache;
+mod archive;
+mod filelock;
+mod git;
+mod store;
 mod registry;
 mod integrity;
+mod advisory;
+mod typosquat;
+mod policy;
+mod license;
+mod sbom;
+mod vendor;
+mod bundle;
 mod commands {
     pub mod init;
     pub mod add;
@@ -19,6 +30,15 @@ mod commands {
     pub mod test;
     pub mod download;
     pub mod task;
+    pub mod audit;
+    pub mod licenses;
+    pub mod sbom;
+    pub mod cache;
+    pub mod vendor;
+    pub mod fetch;
+    pub mod bundle;
+    pub mod verify;
+    pub mod check;
 }
 use clap::{Parser, Subcommand};
 use colored::*;
@@ -46,6 +66,18 @@ struct Cli {
     /// Ignore package validation regulations (Security Risk)
     #[arg(long, global = true)]
     ignore_regulations: bool,
+
+    /// Fetch cached git sources even if the requested ref is already present
+    #[arg(long, global = true, conflicts_with = "offline")]
+    refresh: bool,
+
+    /// Never access the network; only use already cached sources
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Number of packages to fetch and install in parallel (defaults to the number of CPUs)
+    #[arg(short, long, global = true, value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: Option<u16>,
 }
 
 #[derive(Subcommand)]
@@ -67,7 +99,21 @@ enum Commands {
         #[arg(long)]
         template: Option<String>,
     },
-    Add { package: String },
+    Add {
+        package: String,
+
+        /// Install only this folder of a monorepo source (e.g. libs/json)
+        #[arg(long)]
+        subdir: Option<String>,
+
+        /// Check out the source's git submodules
+        #[arg(long)]
+        submodules: bool,
+
+        /// Install even if the name looks like a typo of a more popular package
+        #[arg(long)]
+        allow_confusable: bool,
+    },
     Remove { package: String },
     Run { #[arg(last = true)] args: Vec<String> },
     Build { #[arg(last = true)] args: Vec<String> },
@@ -79,6 +125,100 @@ enum Commands {
     Test,
     Download { version: Option<String> },
     Do { task: String, #[arg(last = true)] args: Vec<String> },
+    /// Check locked dependencies against a security advisory database
+    Audit {
+        /// Advisory database directory or git URL
+        #[arg(long)]
+        db: Option<String>,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Advisory ID to ignore (in addition to [audit] ignore in finn.toml)
+        #[arg(long)]
+        ignore: Vec<String>,
+    },
+    /// List dependency licenses and check them against the deny-list
+    Licenses {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Write a combined third-party NOTICE file
+        #[arg(long, value_name = "FILE")]
+        notice: Option<String>,
+
+        /// SPDX identifier to deny (in addition to [licenses] deny in finn.toml)
+        #[arg(long)]
+        deny: Vec<String>,
+    },
+    /// Export a software bill of materials from finn.lock
+    Sbom {
+        /// Output format: 'cyclonedx-json' or 'spdx-json'
+        #[arg(long, default_value = "cyclonedx-json")]
+        format: String,
+
+        /// Write to a file instead of stdout
+        #[arg(long, short = 'o')]
+        output: Option<String>,
+    },
+    /// Check that the current package is ready to publish: metadata, dependencies and package contents
+    Check {
+        /// Fail on warnings too
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Check installed packages against finn.lock and list changed files
+    Verify {
+        /// Only verify this package
+        package: Option<String>,
+    },
+    /// Download all dependencies into the cache without installing them
+    Fetch,
+    /// Copy every locked package into a directory for offline, hermetic builds
+    Vendor {
+        /// Directory to vendor into
+        #[arg(default_value = "vendor")]
+        dir: String,
+    },
+    /// Inspect and maintain the global package cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Move cached dependencies between machines as a single file
+    Bundle {
+        #[command(subcommand)]
+        action: BundleAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum BundleAction {
+    /// Pack every cached source needed by finn.lock into a bundle file
+    Create {
+        #[arg(default_value = "deps.finnbundle")]
+        file: String,
+    },
+    /// Seed the cache from a bundle file (--force replaces entries that are already cached)
+    Import { file: String },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cached sources with their size and last use
+    List,
+    /// Check cached sources against the recorded checksums
+    Verify,
+    /// Remove stale and untracked cache entries
+    Prune {
+        /// Only remove entries unused for longer than this (e.g. 30d, 12h)
+        #[arg(long)]
+        older_than: Option<String>,
+    },
+    /// Remove every cached source
+    Clean,
 }
 
 pub struct FinnContext {
@@ -86,6 +226,9 @@ pub struct FinnContext {
     pub quiet: bool,
     pub force: bool,
     pub ignore_regulations: bool,
+    pub refresh: bool,
+    pub offline: bool,
+    pub jobs: usize,
 }
 
 fn main() {
@@ -96,11 +239,15 @@ fn main() {
         quiet: cli.quiet,
         force: cli.force,
         ignore_regulations: cli.ignore_regulations,
+        refresh: cli.refresh,
+        offline: cli.offline,
+        jobs: cli.jobs.map(usize::from)
+            .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4)),
     };
 
     let result = match cli.command {
         Commands::Init { path, yes, name, template } => commands::init::run(&path, yes, name, template, &ctx),
-        Commands::Add { package } => commands::add::run(&package, &ctx),
+        Commands::Add { package, subdir, submodules, allow_confusable } => commands::add::run(&package, subdir, submodules, allow_confusable, &ctx),
         Commands::Remove { package } => commands::remove::run(&package, &ctx),
         Commands::Run { args } => commands::run::run(args, &ctx),
         Commands::Build { args } => commands::build::run(args, &ctx),
@@ -112,15 +259,34 @@ fn main() {
         Commands::Test => commands::test::run(&ctx),
         Commands::Download { version } => commands::download::run(version, &ctx),
         Commands::Do { task, args } => commands::task::run(&task, args, &ctx),
+        Commands::Audit { db, json, ignore } => commands::audit::run(db, json, ignore, &ctx),
+        Commands::Licenses { json, notice, deny } => commands::licenses::run(json, notice, deny, &ctx),
+        Commands::Sbom { format, output } => commands::sbom::run(&format, output, &ctx),
+        Commands::Check { strict } => commands::check::run(strict, &ctx),
+        Commands::Verify { package } => commands::verify::run(package, &ctx),
+        Commands::Fetch => commands::fetch::run(&ctx),
+        Commands::Vendor { dir } => commands::vendor::run(&dir, &ctx),
+        Commands::Bundle { action } => match action {
+            BundleAction::Create { file } => commands::bundle::create(&file, &ctx),
+            BundleAction::Import { file } => commands::bundle::import(&file, &ctx),
+        },
+        Commands::Cache { action } => match action {
+            CacheAction::List => commands::cache::list(&ctx),
+            CacheAction::Verify => commands::cache::verify(&ctx),
+            CacheAction::Prune { older_than } => commands::cache::prune(older_than, &ctx),
+            CacheAction::Clean => commands::cache::clean(&ctx),
+        },
     };
 
     if let Err(e) = result {
         if !ctx.quiet {
-            eprintln!("{} {}", "[ERROR]".red().bold(), e);
             if ctx.verbose {
+                eprintln!("{} {}", "[ERROR]".red().bold(), e);
                 for cause in e.chain().skip(1) {
                     eprintln!("  Caused by: {}", cause);
                 }
+            } else {
+                eprintln!("{} {:#}", "[ERROR]".red().bold(), e);
             }
         }
         process::exit(1);
@@ -0,0 +1,183 @@
+use crate::license;
+use crate::utils;
+use crate::validator::PackageType;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow, Context};
+
+pub const POLICY_FILE: &str = "finn-policy.toml";
+
+/// Organization rules for what may be installed, read from `finn-policy.toml`
+/// in the project root or, failing that, `~/.finn/finn-policy.toml`.
+///
+/// ```toml
+/// allowed-sources = ["github.com", "*.corp.example"]
+/// denied-packages = ["left-pad"]
+/// require-checksum = true
+/// allowed-licenses = ["MIT", "Apache-2.0"]
+/// max-depth = 4
+/// allow-c-packages = false
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Policy {
+    /// Hosts dependencies may be fetched from. `local` matches filesystem paths.
+    pub allowed_sources: Option<Vec<String>>,
+    #[serde(default)]
+    pub denied_packages: Vec<String>,
+    #[serde(default)]
+    pub require_checksum: bool,
+    /// SPDX identifiers a dependency's license must be drawn from.
+    pub allowed_licenses: Option<Vec<String>>,
+    pub max_depth: Option<usize>,
+    #[serde(default = "default_true")]
+    pub allow_c_packages: bool,
+
+    #[serde(skip)]
+    pub origin: Option<PathBuf>,
+}
+
+fn default_true() -> bool { true }
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            allowed_sources: None,
+            denied_packages: Vec::new(),
+            require_checksum: false,
+            allowed_licenses: None,
+            max_depth: None,
+            allow_c_packages: true,
+            origin: None,
+        }
+    }
+}
+
+impl Policy {
+    /// Loads the project policy if present, otherwise the global one. No file means no restrictions.
+    pub fn load() -> Result<Self> {
+        let project = Path::new(POLICY_FILE);
+        if project.exists() {
+            return Self::from_file(project);
+        }
+
+        let global = utils::get_home_dir()?.join(".finn").join(POLICY_FILE);
+        if global.exists() {
+            return Self::from_file(&global);
+        }
+
+        Ok(Policy::default())
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let mut policy: Policy = toml::from_str(&content).with_context(|| format!("Failed to parse {:?}", path))?;
+        policy.origin = Some(path.to_path_buf());
+        Ok(policy)
+    }
+
+    fn violation(&self, message: String) -> anyhow::Error {
+        let origin = self.origin.as_ref()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| POLICY_FILE.to_string());
+        anyhow!("Policy violation ({}): {}", origin, message)
+    }
+
+    pub fn check_package(&self, name: &str) -> Result<()> {
+        if self.denied_packages.iter().any(|d| d == name) {
+            return Err(self.violation(format!("package '{}' is listed in denied-packages.", name)));
+        }
+        Ok(())
+    }
+
+    pub fn check_source(&self, name: &str, url: &str) -> Result<()> {
+        let allowed = match &self.allowed_sources {
+            Some(a) => a,
+            None => return Ok(()),
+        };
+
+        let host = source_host(url);
+        if allowed.iter().any(|pattern| host_matches(pattern, &host)) {
+            return Ok(());
+        }
+
+        Err(self.violation(format!(
+            "package '{}' comes from '{}' (host '{}'), which is not in allowed-sources [{}].",
+            name, url, host, allowed.join(", ")
+        )))
+    }
+
+    pub fn check_depth(&self, name: &str, depth: usize) -> Result<()> {
+        if let Some(max) = self.max_depth.filter(|max| depth > *max) {
+            return Err(self.violation(format!(
+                "package '{}' is at dependency depth {}, but max-depth is {}.", name, depth, max
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_package_type(&self, name: &str, package_type: &PackageType) -> Result<()> {
+        if !self.allow_c_packages && matches!(package_type, PackageType::CPackage) {
+            return Err(self.violation(format!(
+                "package '{}' is a C/C++ package and allow-c-packages is false.", name
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_license(&self, name: &str, license: Option<&str>) -> Result<()> {
+        let allowed = match &self.allowed_licenses {
+            Some(a) => a,
+            None => return Ok(()),
+        };
+
+        let license = match license {
+            Some(l) if !l.trim().is_empty() => l,
+            _ => return Err(self.violation(format!(
+                "package '{}' does not declare a license (allowed-licenses: [{}]).", name, allowed.join(", ")
+            ))),
+        };
+
+        if !license::expression_satisfies(license, |id| allowed.iter().any(|a| a.eq_ignore_ascii_case(id))) {
+            return Err(self.violation(format!(
+                "package '{}' is licensed under '{}', which is not covered by allowed-licenses [{}].",
+                name, license, allowed.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_checksum(&self, name: &str, checksum: Option<&str>) -> Result<()> {
+        if self.require_checksum && checksum.is_none_or(|c| c.is_empty()) {
+            return Err(self.violation(format!(
+                "package '{}' has no checksum in finn.lock and require-checksum is true.", name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Extracts the host a source is fetched from. Filesystem paths map to `local`.
+pub fn source_host(source: &str) -> String {
+    if let Some(rest) = source.strip_prefix("git@") {
+        return rest.split(':').next().unwrap_or("").to_lowercase();
+    }
+    if let Ok(parsed) = url::Url::parse(source) {
+        if parsed.scheme() == "file" {
+            return "local".to_string();
+        }
+        if let Some(host) = parsed.host_str() {
+            return host.to_lowercase();
+        }
+    }
+    "local".to_string()
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host.ends_with(&format!(".{}", suffix)),
+        None => pattern == host,
+    }
+}
@@ -19,11 +19,19 @@ pub enum RegistryError {
 #[derive(Deserialize, Debug)]
 pub struct PackageMetadata {
     pub name: String,
+    #[allow(dead_code)]
     pub description: Option<String>,
     pub repo_url: String,
     pub latest_version: Option<String>,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct PopularPackage {
+    pub name: String,
+    #[serde(default)]
+    pub downloads: u64,
+}
+
 pub struct RegistryClient {
     client: Client,
     base_url: String,
@@ -67,4 +75,24 @@ impl RegistryClient {
 
         Ok(metadata)
     }
+
+    /// Fetches the most downloaded packages (used for typosquatting checks).
+    pub fn get_popular_packages(&self) -> Result<Vec<PopularPackage>> {
+        let url = format!("{}/api/packages/popular", self.base_url);
+
+        let response = self.client
+            .get(&url)
+            .header("User-Agent", "finn-cli/0.5.0")
+            .send()
+            .map_err(|e| RegistryError::NetworkError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(RegistryError::ApiError(format!("Status {}", response.status())).into());
+        }
+
+        let packages: Vec<PopularPackage> = response.json()
+            .context("Failed to parse registry response")?;
+
+        Ok(packages)
+    }
 }
@@ -0,0 +1,276 @@
+use crate::archive;
+use crate::config::FinnConfig;
+use crate::integrity::{self, HashAlgorithm};
+use crate::license;
+use crate::lock::FinnLock;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use serde_json::{json, Value};
+use sha2::{Sha256, Digest};
+
+/// One entry of the dependency graph, flattened from finn.lock.
+struct Component {
+    name: String,
+    version: String,
+    source: String,
+    commit: String,
+    checksum: String,
+    license: Option<String>,
+    dependencies: Vec<String>,
+}
+
+/// Everything needed to render an SBOM, with components sorted by name.
+pub struct Inventory {
+    root_name: String,
+    root_version: String,
+    root_license: Option<String>,
+    root_dependencies: Vec<String>,
+    components: Vec<Component>,
+    lock_digest: String,
+}
+
+impl Inventory {
+    pub fn collect(config: &FinnConfig, lock: &FinnLock, packages_dir: &Path) -> Self {
+        let mut names: Vec<&String> = lock.packages.keys().collect();
+        names.sort();
+
+        let mut digest = Sha256::new();
+        let components = names.into_iter().map(|name| {
+            let locked = &lock.packages[name];
+            let root = packages_dir.join(name);
+            let pkg_config = FinnConfig::from_file(&root.join("finn.toml")).ok();
+            let license = license::detect_package_license(&root, pkg_config.as_ref()).expression;
+
+            // Digest the lock contents in a stable order for the SPDX namespace
+            for part in [name, &locked.version, &locked.source, &locked.commit, &locked.checksum] {
+                digest.update(part.as_bytes());
+                digest.update([0]);
+            }
+
+            let mut dependencies = locked.dependencies.clone();
+            dependencies.sort();
+            Component {
+                name: name.clone(),
+                version: locked.version.clone(),
+                source: locked.source.clone(),
+                commit: locked.commit.clone(),
+                checksum: locked.checksum.clone(),
+                license,
+                dependencies,
+            }
+        }).collect();
+
+        let mut root_dependencies: Vec<String> = config.packages.as_ref()
+            .map(|p| p.keys().cloned().collect())
+            .unwrap_or_default();
+        root_dependencies.sort();
+
+        Inventory {
+            root_name: config.project.name.clone(),
+            root_version: config.project.version.clone(),
+            root_license: config.project.license.clone(),
+            root_dependencies,
+            components,
+            lock_digest: hex::encode(digest.finalize()),
+        }
+    }
+
+    /// CycloneDX 1.5 JSON
+    pub fn to_cyclonedx(&self) -> Value {
+        let root_ref = format!("{}@{}", self.root_name, self.root_version);
+        let bom_ref = |name: &str| -> String {
+            self.components.iter()
+                .find(|c| c.name == name)
+                .map(|c| format!("{}@{}", c.name, c.version))
+                .unwrap_or_else(|| name.to_string())
+        };
+
+        let components: Vec<Value> = self.components.iter().map(|c| {
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": bom_ref(&c.name),
+                "name": c.name,
+                "version": c.version,
+                "externalReferences": [{
+                    "type": if archive::is_archive_url(&c.source) { "distribution" } else { "vcs" },
+                    "url": c.source,
+                }],
+                "properties": [{ "name": "finn:commit", "value": c.commit }],
+            });
+            if let Some((algorithm, digest)) = integrity::digest_parts(&c.checksum) {
+                let alg = match algorithm { HashAlgorithm::Sha256 => "SHA-256", HashAlgorithm::Blake3 => "BLAKE3" };
+                component["hashes"] = json!([{ "alg": alg, "content": digest }]);
+            }
+            if let Some(l) = &c.license {
+                component["licenses"] = json!([{ "expression": l }]);
+            }
+            component
+        }).collect();
+
+        let mut dependencies = vec![json!({
+            "ref": root_ref,
+            "dependsOn": self.root_dependencies.iter().map(|d| bom_ref(d)).collect::<Vec<_>>(),
+        })];
+        for c in &self.components {
+            dependencies.push(json!({
+                "ref": bom_ref(&c.name),
+                "dependsOn": c.dependencies.iter().map(|d| bom_ref(d)).collect::<Vec<_>>(),
+            }));
+        }
+
+        let mut root = json!({
+            "type": "application",
+            "bom-ref": root_ref,
+            "name": self.root_name,
+            "version": self.root_version,
+        });
+        if let Some(l) = &self.root_license {
+            root["licenses"] = json!([{ "expression": l }]);
+        }
+
+        let mut metadata = json!({
+            "tools": { "components": [{ "type": "application", "name": "finn", "version": env!("CARGO_PKG_VERSION") }] },
+            "component": root,
+        });
+        // Only stamp a time when the build asks for one, so repeated runs stay identical
+        if let Some(ts) = source_date_epoch() {
+            metadata["timestamp"] = json!(ts);
+        }
+
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": metadata,
+            "components": components,
+            "dependencies": dependencies,
+        })
+    }
+
+    /// SPDX 2.3 JSON
+    pub fn to_spdx(&self) -> Value {
+        let root_id = "SPDXRef-RootPackage".to_string();
+        let mut packages = vec![json!({
+            "SPDXID": root_id,
+            "name": self.root_name,
+            "versionInfo": self.root_version,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": self.root_license.clone().unwrap_or_else(|| "NOASSERTION".to_string()),
+            "copyrightText": "NOASSERTION",
+            "primaryPackagePurpose": "APPLICATION",
+        })];
+
+        let mut relationships = vec![json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": root_id,
+        })];
+        for dep in &self.root_dependencies {
+            relationships.push(json!({
+                "spdxElementId": root_id,
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": spdx_id(dep),
+            }));
+        }
+
+        for c in &self.components {
+            let mut package = json!({
+                "SPDXID": spdx_id(&c.name),
+                "name": c.name,
+                "versionInfo": c.version,
+                "downloadLocation": download_location(&c.source, &c.commit),
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": c.license.clone().unwrap_or_else(|| "NOASSERTION".to_string()),
+                "copyrightText": "NOASSERTION",
+                "primaryPackagePurpose": "LIBRARY",
+            });
+            if let Some((algorithm, digest)) = integrity::digest_parts(&c.checksum) {
+                let alg = match algorithm { HashAlgorithm::Sha256 => "SHA256", HashAlgorithm::Blake3 => "BLAKE3" };
+                package["checksums"] = json!([{ "algorithm": alg, "checksumValue": digest }]);
+            }
+            packages.push(package);
+
+            for dep in &c.dependencies {
+                relationships.push(json!({
+                    "spdxElementId": spdx_id(&c.name),
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": spdx_id(dep),
+                }));
+            }
+        }
+
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": format!("{}-{}", self.root_name, self.root_version),
+            "documentNamespace": format!(
+                "https://spdx.org/spdxdocs/{}-{}-{}",
+                self.root_name, self.root_version, &self.lock_digest[0..16]
+            ),
+            "creationInfo": {
+                // SPDX requires a creation time; default to the epoch for reproducibility
+                "created": source_date_epoch().unwrap_or_else(|| "1970-01-01T00:00:00Z".to_string()),
+                "creators": [format!("Tool: finn-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+}
+
+pub fn render(format: &str, inventory: &Inventory) -> Result<String> {
+    let doc = match format {
+        "cyclonedx-json" => inventory.to_cyclonedx(),
+        "spdx-json" => inventory.to_spdx(),
+        other => return Err(anyhow!("Unknown SBOM format '{}'. Use 'cyclonedx-json' or 'spdx-json'.", other)),
+    };
+    Ok(serde_json::to_string_pretty(&doc)? + "\n")
+}
+
+fn spdx_id(name: &str) -> String {
+    let cleaned: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+        .collect();
+    format!("SPDXRef-Package-{}", cleaned)
+}
+
+fn download_location(source: &str, commit: &str) -> String {
+    if archive::is_archive_url(source) && !source.starts_with("file://") {
+        return source.to_string();
+    }
+    if source.starts_with("http") || source.starts_with("ssh://") || source.starts_with("git@") {
+        if commit.is_empty() || commit == "unknown" {
+            format!("git+{}", source)
+        } else {
+            format!("git+{}@{}", source, commit)
+        }
+    } else {
+        // Local paths are not meaningful outside this machine
+        "NOASSERTION".to_string()
+    }
+}
+
+/// RFC 3339 time from `SOURCE_DATE_EPOCH`, the reproducible-builds convention.
+fn source_date_epoch() -> Option<String> {
+    let secs: i64 = std::env::var("SOURCE_DATE_EPOCH").ok()?.trim().parse().ok()?;
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+
+    // Civil-from-days (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    Some(format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, rem / 3600, (rem % 3600) / 60, rem % 60))
+}
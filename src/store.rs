@@ -0,0 +1,170 @@
+use crate::config::GlobalConfig;
+use crate::filelock::{self, FileLock};
+use crate::integrity;
+use crate::utils;
+use crate::validator;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use anyhow::{Result, anyhow, Context};
+use indicatif::MultiProgress;
+
+/// How files are placed from the store into a project's packages directory.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMode {
+    /// Hardlink, then reflink, then copy: whatever the filesystem supports first
+    #[default]
+    Auto,
+    Hardlink,
+    Reflink,
+    Copy,
+}
+
+/// `~/.finn/store`: one directory per package checksum, never modified once created.
+/// Entries are named by `entry_name`, since checksums contain a `:`.
+pub fn get_store_dir() -> Result<PathBuf> {
+    let store = utils::get_home_dir()?.join(".finn").join("store");
+    if !store.exists() {
+        fs::create_dir_all(&store)?;
+    }
+    Ok(store)
+}
+
+/// Directory name of the store entry for `checksum`.
+pub fn entry_name(checksum: &str) -> String {
+    checksum.replace(':', "-")
+}
+
+/// The checksum a store directory stands for: its entry's, or for a staging directory
+/// (`.tmp-<entry>-<pid>-<n>`) the one of the entry being created in it.
+pub fn dir_checksum(name: &str) -> String {
+    match name.strip_prefix(".tmp-").and_then(|rest| rest.rsplitn(3, '-').nth(2)) {
+        Some(entry) => entry_checksum(entry),
+        None => entry_checksum(name),
+    }
+}
+
+/// The checksum a store entry name stands for; older entries are named by an unprefixed checksum.
+fn entry_checksum(name: &str) -> String {
+    match name.rsplit_once('-') {
+        Some((format, digest)) => format!("{}:{}", format, digest),
+        None => name.to_string(),
+    }
+}
+
+/// First characters of a checksum's digest, for messages.
+pub fn short(checksum: &str) -> &str {
+    let digest = checksum.rsplit([':', '-']).next().unwrap_or(checksum);
+    &digest[..digest.len().min(12)]
+}
+
+/// A package store entry. It stays locked until this is dropped, so `finn cache clean` cannot
+/// remove it while it is linked into a project.
+pub struct StoredPackage {
+    pub path: PathBuf,
+    _lock: FileLock,
+}
+
+/// Adds the package files at `source` (see `integrity::PackageFilter`) to the store.
+pub fn store_package(source: &Path, verbose: bool, progress: Option<&MultiProgress>) -> Result<StoredPackage> {
+    let store = get_store_dir()?;
+    let checksum = integrity::calculate_package_hash(source)?;
+    let mut lock = filelock::store_entry(&checksum, progress)?;
+    let entry = store.join(entry_name(&checksum));
+    if entry.exists() {
+        if verbose { println!("   Found {} in store", short(&checksum)); }
+        return Ok(StoredPackage { path: entry, _lock: lock });
+    }
+
+    // Copy into a private directory first and name it after what was actually copied,
+    // so a source that changes mid-copy can never produce a mislabeled entry
+    // Unique per process and per call, since parallel installs may store identical packages at once
+    static STAGING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let staging = store.join(format!(
+        ".tmp-{}-{}-{}", entry_name(&checksum), std::process::id(), STAGING_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    copy_tree(source, &staging, LinkMode::Copy)
+        .inspect_err(|_| { let _ = fs::remove_dir_all(&staging); })
+        .with_context(|| format!("Failed to add {:?} to the package store", source))?;
+    let copied = integrity::calculate_package_hash(&staging)?;
+    if copied != checksum {
+        // Released first: locks conflict even within one process
+        drop(lock);
+        lock = filelock::store_entry(&copied, progress)?;
+    }
+    let entry = store.join(entry_name(&copied));
+
+    if entry.exists() {
+        fs::remove_dir_all(&staging)?;
+    } else {
+        fs::rename(&staging, &entry).context("Failed to finalize package store entry")?;
+        if verbose { println!("   Stored {} as {}", source.display(), short(&copied)); }
+    }
+    Ok(StoredPackage { path: entry, _lock: lock })
+}
+
+/// Materializes a store entry at `dest` using the configured link mode.
+pub fn link_into(entry: &Path, dest: &Path) -> Result<()> {
+    let mode = GlobalConfig::load()?.store.link_mode;
+    copy_tree(entry, dest, mode).with_context(|| format!("Failed to install {:?} from the package store", dest))
+}
+
+/// Recreates the package files below `src` at `dest`: the same files its checksum covers,
+/// so `.git` and ignored files stay behind. Symlinks are copied as links, and only if they
+/// stay inside the package.
+fn copy_tree(src: &Path, dest: &Path, mode: LinkMode) -> Result<()> {
+    fs::create_dir_all(dest)?;
+    for (relative, path) in integrity::package_files(src)? {
+        let target = dest.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::symlink_metadata(&path)?.file_type().is_symlink() {
+            validator::check_symlink(&relative, &fs::read_link(&path)?)?;
+            utils::copy_file(&path, &target)
+                .map_err(|e| anyhow!("Failed to place {:?}: {}", target, e))?;
+        } else {
+            place_file(&path, &target, mode)?;
+        }
+    }
+    Ok(())
+}
+
+fn place_file(src: &Path, dest: &Path, mode: LinkMode) -> Result<()> {
+    let result = match mode {
+        LinkMode::Hardlink => fs::hard_link(src, dest),
+        LinkMode::Reflink => reflink(src, dest),
+        LinkMode::Copy => fs::copy(src, dest).map(|_| ()),
+        LinkMode::Auto => fs::hard_link(src, dest)
+            .or_else(|_| reflink(src, dest))
+            .or_else(|_| fs::copy(src, dest).map(|_| ())),
+    };
+    result.map_err(|e| anyhow!("Failed to place {:?} ({:?}): {}", dest, mode, e))
+}
+
+/// A reflinked file starts out with default permissions; keep the executable bit.
+fn reflink(src: &Path, dest: &Path) -> std::io::Result<()> {
+    reflink_copy::reflink(src, dest)?;
+    fs::set_permissions(dest, fs::metadata(src)?.permissions())
+}
+
+/// Store entries whose checksum no longer matches their contents.
+pub fn corrupted_entries() -> Result<Vec<String>> {
+    let mut corrupted = Vec::new();
+    for dir in fs::read_dir(get_store_dir()?)?.filter_map(|e| e.ok()) {
+        let name = dir.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        if !integrity::matches(&dir.path(), &entry_checksum(&name)).unwrap_or(false) {
+            corrupted.push(name);
+        }
+    }
+    corrupted.sort();
+    Ok(corrupted)
+}
@@ -0,0 +1,113 @@
+use crate::registry::PopularPackage;
+
+/// A popular package that the requested name can be confused with.
+#[derive(Debug, Clone)]
+pub struct Confusable {
+    pub name: String,
+    pub downloads: u64,
+    pub reason: String,
+}
+
+/// Compares `requested` against the popular package list and returns the most
+/// downloaded package it could be mistaken for, if any.
+///
+/// A package is only reported if it is more popular than the requested one, so
+/// installing the popular original never triggers a warning.
+pub fn find_confusable(requested: &str, popular: &[PopularPackage]) -> Option<Confusable> {
+    let requested_downloads = popular.iter()
+        .find(|p| p.name == requested)
+        .map(|p| p.downloads)
+        .unwrap_or(0);
+
+    let requested_plain = strip_separators(&requested.to_lowercase());
+    let requested_skeleton = skeleton(requested);
+
+    let mut best: Option<Confusable> = None;
+
+    for candidate in popular {
+        if candidate.name == requested || candidate.downloads <= requested_downloads {
+            continue;
+        }
+
+        let candidate_plain = strip_separators(&candidate.name.to_lowercase());
+        let candidate_skeleton = skeleton(&candidate.name);
+
+        let reason = if candidate_plain == requested_plain {
+            "differs only by case or separators".to_string()
+        } else if candidate_skeleton == requested_skeleton {
+            "uses look-alike characters".to_string()
+        } else {
+            let distance = edit_distance(&requested_skeleton, &candidate_skeleton);
+            let threshold = if candidate_skeleton.chars().count() <= 8 { 1 } else { 2 };
+            // Very short names are too close to everything to compare meaningfully
+            if candidate_skeleton.chars().count() < 4 || distance > threshold {
+                continue;
+            }
+            format!("is {} edit{} away", distance, if distance == 1 { "" } else { "s" })
+        };
+
+        if best.as_ref().is_none_or(|b| candidate.downloads > b.downloads) {
+            best = Some(Confusable {
+                name: candidate.name.clone(),
+                downloads: candidate.downloads,
+                reason,
+            });
+        }
+    }
+
+    best
+}
+
+fn strip_separators(name: &str) -> String {
+    name.chars().filter(|c| !matches!(c, '-' | '_' | '.')).collect()
+}
+
+/// Reduces a name to a canonical form where visually similar characters collapse
+/// together (e.g. `fin-http`, `Fin_Http` and `fln-htTp` share a skeleton).
+fn skeleton(name: &str) -> String {
+    let mapped: String = name.to_lowercase()
+        .chars()
+        .filter(|c| !matches!(c, '-' | '_' | '.'))
+        .map(|c| match c {
+            '0' | 'о' | 'ο' => 'o',
+            '1' | 'i' | '|' | 'і' | 'ı' | 'ι' => 'l',
+            '3' | 'е' | 'ε' => 'e',
+            '5' | 'ѕ' => 's',
+            '4' | 'а' | 'α' => 'a',
+            '7' => 't',
+            '8' => 'b',
+            'р' | 'ρ' => 'p',
+            'с' | 'ϲ' => 'c',
+            'х' | 'χ' => 'x',
+            'у' | 'γ' => 'y',
+            'ј' => 'j',
+            'к' | 'κ' => 'k',
+            'м' => 'm',
+            'н' => 'h',
+            'т' | 'τ' => 't',
+            'ν' => 'v',
+            other => other,
+        })
+        .collect();
+
+    mapped.replace("rn", "m").replace("vv", "w").replace("cl", "d")
+}
+
+/// Levenshtein distance over chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
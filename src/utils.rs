@@ -1,5 +1,5 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use anyhow::{Result, anyhow};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::time::Duration;
@@ -49,3 +49,81 @@ pub fn find_compiler() -> Result<String> {
 
     Err(anyhow!("Fin compiler not found.\nRun 'finn download' to install the latest version."))
 }
+
+/// Parses sizes like "500MB", "2GB" or "1048576" into bytes.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let trimmed = input.trim().to_uppercase();
+    let split = trimmed.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let value: f64 = number.parse().map_err(|_| anyhow!("Invalid size '{}'", input))?;
+
+    let multiplier: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(anyhow!("Invalid size unit in '{}' (use B, KB, MB, GB or TB)", input)),
+    };
+    Ok((value * multiplier as f64) as u64)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", value, UNITS[unit]) }
+}
+
+/// Parses durations like "30d", "12h", "2w" or "90m".
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let trimmed = input.trim();
+    let split = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let value: u64 = number.parse().map_err(|_| anyhow!("Invalid duration '{}'", input))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(anyhow!("Invalid duration unit in '{}' (use s, m, h, d or w)", input)),
+    };
+    Ok(Duration::from_secs(value * seconds))
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Copies the contents of `src` into `dest`, keeping symlinks as links.
+pub fn copy_dir(src: &Path, dest: &Path) -> Result<()> {
+    for entry in walkdir::WalkDir::new(src).min_depth(1) {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(src)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            copy_file(entry.path(), &target).map_err(|e| anyhow!("Failed to copy {:?}: {}", entry.path(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies a file with its permissions. Symlinks are recreated, not followed, where the platform allows.
+pub fn copy_file(src: &Path, dest: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if std::fs::symlink_metadata(src)?.file_type().is_symlink() {
+        return std::os::unix::fs::symlink(std::fs::read_link(src)?, dest);
+    }
+    std::fs::copy(src, dest).map(|_| ())
+}
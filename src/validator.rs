@@ -1,4 +1,9 @@
-use std::path::Path;
+use crate::config::FinnConfig;
+use crate::integrity;
+use crate::license;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path};
 use anyhow::{Result, anyhow};
 use colored::*;
 
@@ -9,37 +14,325 @@ pub enum PackageType {
     Unknown,
 }
 
-pub fn validate_package(path: &Path, ignore_regulations: bool) -> Result<PackageType> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// One problem (or observation) about a package.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Everything `check_package` found out about a package.
+pub struct ValidationReport {
+    pub package_type: PackageType,
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    pub fn add(&mut self, severity: Severity, message: impl Into<String>) {
+        self.findings.push(Finding { severity, message: message.into() });
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Warning)
+    }
+
+    /// Prints warnings and errors, and informational findings if `verbose`.
+    pub fn print(&self, name: &str, verbose: bool) {
+        for finding in &self.findings {
+            let tag = match finding.severity {
+                Severity::Info if !verbose => continue,
+                Severity::Info => "[INFO]".blue(),
+                Severity::Warning => "[WARN]".yellow(),
+                Severity::Error => "[FAIL]".red(),
+            };
+            println!("{} {}: {}", tag, name, finding.message);
+        }
+    }
+}
+
+/// Checks the package at `path` and turns error findings into an `Err`.
+/// With `ignore_regulations` nothing is checked.
+pub fn validate_package(path: &Path, ignore_regulations: bool) -> Result<ValidationReport> {
     if ignore_regulations {
         println!("{} Skipping validation (Regulations Ignored).", "[WARN]".yellow());
-        return Ok(PackageType::Unknown);
+        // Policy still needs the package type, so that is detected anyway
+        return Ok(ValidationReport { package_type: package_type(path), findings: Vec::new() });
     }
 
+    let report = check_package(path);
+    let errors: Vec<String> = report.errors().map(|f| format!("  - {}", f.message)).collect();
+    if !errors.is_empty() {
+        return Err(anyhow!(
+            "Package validation failed:\n{}\nUse --ignore-regulations to force installation.",
+            errors.join("\n")
+        ));
+    }
+    Ok(report)
+}
+
+/// Inspects the package at `path`: its manifests, the files they reference, license, README
+/// and any committed binaries. Never fails; problems are reported as findings.
+pub fn check_package(path: &Path) -> ValidationReport {
+    inspect(path, false)
+}
+
+/// Like `check_package`, but for a package about to be published: the name, version,
+/// description, license and repository in finn.toml are required, so problems with them are errors.
+pub fn check_release(path: &Path) -> ValidationReport {
+    inspect(path, true)
+}
+
+fn inspect(path: &Path, release: bool) -> ValidationReport {
+    let metadata = if release { Severity::Error } else { Severity::Warning };
     let has_finn_toml = path.join("finn.toml").exists();
     let has_pkg_json = path.join("package.json").exists();
     let has_exports = path.join("exports.fin").exists();
-    
-    // C/C++ Checks
-    let has_cmake = path.join("CMakeLists.txt").exists();
-    let has_makefile = path.join("Makefile").exists();
+    let mut report = ValidationReport { package_type: package_type(path), findings: Vec::new() };
+
+    if matches!(report.package_type, PackageType::Unknown) {
+        report.add(Severity::Error, "The repository does not look like a valid Fin package or C library. \
+            Missing: finn.toml, package.json, exports.fin, CMakeLists.txt, or Makefile.");
+        return report;
+    }
+    if matches!(report.package_type, PackageType::CPackage) {
+        report.add(Severity::Info, "Detected C/C++ build system.");
+    }
+
+    let config = if has_finn_toml { check_finn_toml(path, metadata, &mut report) } else { None };
+    if has_exports {
+        check_exports(path, &mut report);
+    }
+    if has_pkg_json {
+        check_package_json(path, &mut report);
+    }
+
+    if release && config.as_ref().is_some_and(|c| c.project.entrypoint.is_none()) && !has_exports && !has_pkg_json {
+        report.add(Severity::Error, "No entrypoint: set `entrypoint` in finn.toml or add an exports.fin.");
+    }
+    if license::detect_package_license(path, config.as_ref()).expression.is_none() {
+        report.add(metadata, "No license declared: set `license` in finn.toml or add a LICENSE file.");
+    }
+    let has_readme = fs::read_dir(path).into_iter().flatten().filter_map(|e| e.ok())
+        .any(|e| e.file_name().to_string_lossy().to_ascii_lowercase().starts_with("readme"));
+    if !has_readme {
+        report.add(Severity::Warning, "No README found.");
+    }
+
+    check_files(path, &mut report);
+    report
+}
 
-    if has_finn_toml {
-        // Check if it claims to be a package or project
-        // For now, assume valid Fin Project
-        return Ok(PackageType::FinProject);
+/// What kind of package `path` holds, judged by its manifest or build files.
+pub fn package_type(path: &Path) -> PackageType {
+    if path.join("finn.toml").exists() {
+        PackageType::FinProject
+    } else if path.join("package.json").exists() || path.join("exports.fin").exists() {
+        PackageType::FinPackage
+    } else if path.join("CMakeLists.txt").exists() || path.join("Makefile").exists() {
+        PackageType::CPackage
+    } else {
+        PackageType::Unknown
     }
+}
 
-    if has_pkg_json || has_exports {
-        return Ok(PackageType::FinPackage);
+/// `metadata` is the severity of problems with the name, version, description and repository.
+fn check_finn_toml(path: &Path, metadata: Severity, report: &mut ValidationReport) -> Option<FinnConfig> {
+    let config = match FinnConfig::from_file(&path.join("finn.toml")) {
+        Ok(c) => c,
+        Err(e) => {
+            report.add(Severity::Error, format!("finn.toml is invalid: {:#}", e));
+            return None;
+        }
+    };
+
+    let project = &config.project;
+    if let Err(e) = validate_package_name(&project.name) {
+        report.add(metadata, format!("Project name: {}", e));
+    }
+    if semver::Version::parse(&project.version).is_err() {
+        report.add(metadata, format!("Version '{}' is not a semantic version (e.g. 1.2.0).", project.version));
+    }
+    if metadata == Severity::Error {
+        if project.description.as_deref().is_none_or(|d| d.trim().is_empty()) {
+            report.add(metadata, "No description: set `description` in finn.toml.");
+        }
+        match project.repository.as_deref() {
+            None => report.add(metadata, "No repository: set `repository` in finn.toml to the package's source URL."),
+            Some(url) if !["https://", "http://", "ssh://", "git://", "git@"].iter().any(|p| url.starts_with(p)) => {
+                report.add(metadata, format!("Repository '{}' is not a URL.", url));
+            }
+            Some(_) => {}
+        }
+    }
+    if let Some(entry) = &project.entrypoint
+        && !path.join("src").join(entry).is_file()
+        && !path.join(entry).is_file()
+    {
+        report.add(Severity::Error, format!("Entrypoint '{}' does not exist (looked in src/ and the package root).", entry));
     }
+    if let Some(file) = &project.license_file
+        && !path.join(file).is_file()
+    {
+        report.add(Severity::Error, format!("license-file '{}' does not exist.", file));
+    }
+    for name in config.packages.iter().flat_map(|p| p.keys()) {
+        if let Err(e) = validate_package_name(name) {
+            report.add(Severity::Error, format!("Dependency: {}", e));
+        }
+    }
+    Some(config)
+}
+
+/// exports.fin holds `export` statements, e.g. `export * from "src/lib";`.
+/// Every referenced module must exist, with or without its `.fin` extension.
+fn check_exports(path: &Path, report: &mut ValidationReport) {
+    let content = match fs::read_to_string(path.join("exports.fin")) {
+        Ok(c) => c,
+        Err(e) => {
+            report.add(Severity::Error, format!("exports.fin cannot be read: {}", e));
+            return;
+        }
+    };
 
-    if has_cmake || has_makefile {
-        println!("{} Detected C/C++ build system.", "[INFO]".blue());
-        return Ok(PackageType::CPackage);
+    for (number, line) in content.lines().enumerate() {
+        let statement = line.split("//").next().unwrap_or("").trim().trim_end_matches(';').trim();
+        if statement.is_empty() {
+            continue;
+        }
+        if !statement.starts_with("export") {
+            report.add(Severity::Warning, format!("exports.fin:{}: expected an `export` statement, found '{}'.", number + 1, statement));
+            continue;
+        }
+        if let Some((_, module)) = statement.split_once(" from ") {
+            let module = module.trim().trim_matches('"');
+            let target = path.join(module);
+            if !target.is_file() && !target.with_extension("fin").is_file() {
+                report.add(Severity::Error, format!("exports.fin:{}: module '{}' does not exist.", number + 1, module));
+            }
+        }
     }
+}
 
-    Err(anyhow!("Package validation failed. \n\
-        The repository does not look like a valid Fin package or C library.\n\
-        Missing: finn.toml, package.json, exports.fin, CMakeLists.txt, or Makefile.\n\
-        Use --ignore-regulations to force installation."))
+fn check_package_json(path: &Path, report: &mut ValidationReport) {
+    let manifest: serde_json::Value = match fs::read_to_string(path.join("package.json")).map_err(anyhow::Error::from)
+        .and_then(|c| serde_json::from_str(&c).map_err(anyhow::Error::from))
+    {
+        Ok(v) => v,
+        Err(e) => {
+            report.add(Severity::Error, format!("package.json is invalid: {}", e));
+            return;
+        }
+    };
+    if manifest.get("name").and_then(|n| n.as_str()).is_none() {
+        report.add(Severity::Warning, "package.json has no \"name\".");
+    }
+    if let Some(main) = manifest.get("main").and_then(|m| m.as_str())
+        && !path.join(main).is_file()
+    {
+        report.add(Severity::Error, format!("package.json \"main\" file '{}' does not exist.", main));
+    }
+}
+
+/// Compiled programs and libraries, by their magic numbers: ELF, PE, Mach-O (thin and fat).
+const BINARY_MAGIC: [&[u8]; 7] = [
+    b"\x7fELF", b"MZ",
+    b"\xfe\xed\xfa\xce", b"\xfe\xed\xfa\xcf", b"\xce\xfa\xed\xfe", b"\xcf\xfa\xed\xfe", b"\xca\xfe\xba\xbe",
+];
+
+/// Flags committed binaries and executables that are not scripts.
+fn check_files(path: &Path, report: &mut ValidationReport) {
+    let files = match integrity::package_files(path) {
+        Ok(f) => f,
+        Err(e) => {
+            report.add(Severity::Error, format!("{:#}", e));
+            return;
+        }
+    };
+
+    for (relative, file) in files {
+        let Ok(metadata) = fs::symlink_metadata(&file) else { continue };
+        if !metadata.is_file() {
+            continue;
+        }
+        let mut head = [0u8; 4];
+        let read = fs::File::open(&file).and_then(|mut f| f.read(&mut head)).unwrap_or(0);
+        let head = &head[..read];
+
+        if BINARY_MAGIC.iter().any(|magic| head.starts_with(magic)) {
+            report.add(Severity::Warning, format!("'{}' is a compiled binary; packages should ship sources.", relative));
+        } else if integrity::is_executable(&metadata) && !head.starts_with(b"#!") {
+            report.add(Severity::Warning, format!("'{}' is executable but is not a script (no #! line).", relative));
+        }
+    }
+}
+
+/// Longest accepted package name.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Names Windows reserves for devices, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul",
+    "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+    "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Package names become directory names under `.finn/packages`, in the cache and in vendor
+/// directories, so only plain names are accepted: ASCII letters, digits, `-`, `_` and `.`,
+/// starting with a letter or digit, at most 64 characters, and no device names such as `con`.
+pub fn validate_package_name(name: &str) -> Result<()> {
+    let problem = if name.is_empty() {
+        Some("it is empty".to_string())
+    } else if name.len() > MAX_NAME_LENGTH {
+        Some(format!("it is longer than {} characters", MAX_NAME_LENGTH))
+    } else if !name.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        Some("it must start with a letter or digit".to_string())
+    } else if let Some(c) = name.chars().find(|c| !c.is_ascii_alphanumeric() && !matches!(c, '-' | '_' | '.')) {
+        Some(format!("'{}' is not allowed (use letters, digits, '-', '_' and '.')", c.escape_default()))
+    } else if RESERVED_NAMES.contains(&name.split('.').next().unwrap_or(name).to_ascii_lowercase().as_str()) {
+        Some("it is a reserved device name on Windows".to_string())
+    } else if name.ends_with('.') {
+        Some("it must not end with '.'".to_string())
+    } else {
+        None
+    };
+
+    match problem {
+        Some(p) => Err(anyhow!("Invalid package name '{}': {}.", name.escape_default(), p)),
+        None => Ok(()),
+    }
+}
+
+/// Rejects a symlink at `relative` (a path inside a package) whose target is absolute or
+/// climbs out of the package root, so installing a package never exposes files outside it.
+/// `..` is only accepted at the start of the target: after a name it would apply to wherever
+/// that name (possibly another symlink) leads, which cannot be checked from the text alone.
+pub fn check_symlink(relative: &str, target: &Path) -> Result<()> {
+    let escapes = || anyhow!(
+        "Package contains a symlink '{}' -> '{}' that points outside the package.",
+        relative, target.display()
+    );
+
+    // Depth below the package root of the directory holding the link
+    let mut depth = Path::new(relative).components().count().saturating_sub(1);
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => descended = true,
+            Component::CurDir => {}
+            Component::ParentDir if !descended => depth = depth.checked_sub(1).ok_or_else(escapes)?,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return Err(escapes()),
+        }
+    }
+    Ok(())
 }
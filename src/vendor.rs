@@ -0,0 +1,101 @@
+use crate::integrity::{self, FileManifest, HashFormat};
+use crate::utils;
+use crate::validator;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, anyhow, Context};
+use tempfile::TempDir;
+
+/// Written into every vendored package directory.
+pub const CHECKSUM_FILE: &str = ".finn-checksum.json";
+
+/// Contents of `.finn-checksum.json`: the package checksum from finn.lock and a digest per file,
+/// in the same format as that checksum.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VendorChecksum {
+    pub package: String,
+    pub files: BTreeMap<String, String>,
+}
+
+/// Copies the installed package at `installed` to `dest` and records its checksums.
+/// Fails if the installed files no longer match the checksum locked for them.
+pub fn vendor_package(installed: &Path, dest: &Path, checksum: &str) -> Result<()> {
+    let manifest = FileManifest::compute(installed, HashFormat::of(checksum)?)?;
+    if manifest.checksum != checksum {
+        return Err(anyhow!(
+            "Installed package {:?} does not match finn.lock (expected {}, found {}). Run 'finn verify' to see what changed.",
+            installed, checksum, manifest.checksum
+        ));
+    }
+
+    if dest.exists() {
+        fs::remove_dir_all(dest).with_context(|| format!("Failed to clear {:?}", dest))?;
+    }
+    fs::create_dir_all(dest)?;
+
+    for relative in manifest.files.keys() {
+        let target = dest.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        utils::copy_file(&installed.join(relative), &target).with_context(|| format!("Failed to write {:?}", target))?;
+    }
+
+    let record = VendorChecksum { package: manifest.checksum, files: manifest.files };
+    fs::write(dest.join(CHECKSUM_FILE), serde_json::to_string_pretty(&record)?)?;
+    Ok(())
+}
+
+/// Checks the vendored package in `dir` against its `.finn-checksum.json` and the locked checksum.
+/// Returns a copy without the checksum file, ready to be added to the store.
+pub fn stage_package(dir: &Path, checksum: &str) -> Result<TempDir> {
+    let record_path = dir.join(CHECKSUM_FILE);
+    let record: VendorChecksum = serde_json::from_str(
+        &fs::read_to_string(&record_path).with_context(|| format!("Missing {:?}. Run 'finn vendor' again.", record_path))?
+    ).with_context(|| format!("Failed to parse {:?}", record_path))?;
+
+    if record.package != checksum {
+        return Err(anyhow!(
+            "Vendored package {:?} was made from a different version than finn.lock records. Run 'finn vendor' again.",
+            dir
+        ));
+    }
+
+    let format = HashFormat::of(checksum)?;
+    let staging = TempDir::new()?;
+    let mut seen = 0;
+    for (relative, path) in integrity::package_files(dir)? {
+        if relative == CHECKSUM_FILE {
+            continue;
+        }
+        match record.files.get(&relative) {
+            Some(expected) if *expected == integrity::file_digest(&path, format)? => {}
+            Some(_) => return Err(modified(dir, &format!("'{}' was changed", relative))),
+            None => return Err(modified(dir, &format!("'{}' was added", relative))),
+        }
+        if fs::symlink_metadata(&path)?.file_type().is_symlink() {
+            validator::check_symlink(&relative, &fs::read_link(&path)?)?;
+        }
+        let target = staging.path().join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        utils::copy_file(&path, &target).with_context(|| format!("Failed to write {:?}", target))?;
+        seen += 1;
+    }
+    if seen != record.files.len() {
+        let missing = record.files.keys().find(|f| fs::symlink_metadata(dir.join(f)).is_err()).cloned().unwrap_or_default();
+        return Err(modified(dir, &format!("'{}' was removed", missing)));
+    }
+
+    Ok(staging)
+}
+
+fn modified(dir: &Path, what: &str) -> anyhow::Error {
+    anyhow!(
+        "Vendored package {:?} does not match its {}: {}.\nVendored sources must not be edited; run 'finn vendor' to restore them.",
+        dir, CHECKSUM_FILE, what
+    )
+}
@@ -0,0 +1,182 @@
+use predicates::prelude::*;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+mod common;
+use common::{finn, init_app};
+
+const LIB_TOML: &str = r#"
+[project]
+name = "json"
+version = "1.2.0"
+envpath = ".finn"
+entrypoint = "lib.fin"
+"#;
+
+fn tar_bytes(add_extra: impl FnOnce(&mut tar::Builder<Vec<u8>>)) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, content) in [("json-1.2.0/finn.toml", LIB_TOML), ("json-1.2.0/lib.fin", "pub fun parse() {}")] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, content.as_bytes()).unwrap();
+    }
+    add_extra(&mut builder);
+    builder.into_inner().unwrap()
+}
+
+fn write_tar_gz(dir: &Path, file: &str, tar: &[u8]) -> PathBuf {
+    let path = dir.join(file);
+    let mut encoder = flate2::write::GzEncoder::new(fs::File::create(&path).unwrap(), flate2::Compression::default());
+    encoder.write_all(tar).unwrap();
+    encoder.finish().unwrap();
+    path
+}
+
+fn file_url(path: &Path) -> String {
+    format!("file://{}", path.to_str().unwrap().replace("\\", "/"))
+}
+
+fn sha256_of(path: &Path) -> String {
+    hex::encode(Sha256::digest(fs::read(path).unwrap()))
+}
+
+#[test]
+fn test_add_tar_gz_records_sha256() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let archive = write_tar_gz(temp.path(), "json-1.2.0.tar.gz", &tar_bytes(|_| {}));
+    let app = init_app(temp_home.path(), temp.path());
+
+    finn(temp_home.path(), &app)
+        .arg("add").arg(file_url(&archive))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Installed json"));
+
+    // The single top-level directory is stripped
+    assert_eq!(fs::read_to_string(app.join(".finn/packages/json/lib.fin")).unwrap(), "pub fun parse() {}");
+
+    let sha = sha256_of(&archive);
+    let config = fs::read_to_string(app.join("finn.toml")).unwrap();
+    assert!(config.contains(&format!("sha256 = \"{}\"", sha)), "{}", config);
+    let lock = fs::read_to_string(app.join("finn.lock")).unwrap();
+    assert!(lock.contains("version = \"1.2.0\""), "{}", lock);
+    assert!(lock.contains(&format!("sha256 = \"{}\"", sha)), "{}", lock);
+
+    fs::remove_dir_all(app.join(".finn/packages/json")).unwrap();
+    finn(temp_home.path(), &app)
+        .arg("sync")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Integrity verified"));
+}
+
+#[test]
+fn test_tar_xz_and_sha256_mismatch() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let archive = temp.path().join("json-1.2.0.tar.xz");
+    let mut encoder = xz2::write::XzEncoder::new(fs::File::create(&archive).unwrap(), 6);
+    encoder.write_all(&tar_bytes(|_| {})).unwrap();
+    encoder.finish().unwrap();
+
+    let app = init_app(temp_home.path(), temp.path());
+    let config = fs::read_to_string(app.join("finn.toml")).unwrap();
+    let with_dep = |sha: &str| config.replace(
+        "[packages]",
+        &format!("[packages]\njson = {{ url = \"{}\", sha256 = \"{}\" }}", file_url(&archive), sha),
+    );
+
+    fs::write(app.join("finn.toml"), with_dep(&"0".repeat(64))).unwrap();
+    finn(temp_home.path(), &app)
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Checksum mismatch"));
+    assert!(!app.join(".finn/packages/json").exists());
+
+    fs::write(app.join("finn.toml"), with_dep(&sha256_of(&archive))).unwrap();
+    finn(temp_home.path(), &app).arg("sync").assert().success();
+    assert!(app.join(".finn/packages/json/finn.toml").exists());
+}
+
+#[test]
+fn test_changed_archive_replaces_the_installed_package() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let old = write_tar_gz(temp.path(), "json-1.2.0.tar.gz", &tar_bytes(|_| {}));
+    let new = write_tar_gz(temp.path(), "json-1.3.0.tar.gz", &tar_bytes(|builder| {
+        let content = "pub fun stringify() {}";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "json-1.2.0/write.fin", content.as_bytes()).unwrap();
+    }));
+    let app = init_app(temp_home.path(), temp.path());
+    finn(temp_home.path(), &app).arg("add").arg(file_url(&old)).assert().success();
+
+    let config = fs::read_to_string(app.join("finn.toml")).unwrap()
+        .replace(&file_url(&old), &file_url(&new))
+        .replace(&sha256_of(&old), &sha256_of(&new));
+    fs::write(app.join("finn.toml"), config).unwrap();
+    finn(temp_home.path(), &app).arg("sync").assert().success();
+    assert!(app.join(".finn/packages/json/write.fin").exists());
+
+    // The lockfile describes the files that are installed now
+    let lock = fs::read_to_string(app.join("finn.lock")).unwrap();
+    assert!(lock.contains(&format!("sha256 = \"{}\"", sha256_of(&new))), "{}", lock);
+    finn(temp_home.path(), &app).arg("verify").assert().success();
+}
+
+#[test]
+fn test_zip_path_traversal_is_rejected() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let archive = temp.path().join("evil-1.0.zip");
+
+    let mut writer = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    writer.start_file("finn.toml", options).unwrap();
+    writer.write_all(LIB_TOML.as_bytes()).unwrap();
+    writer.start_file("../../escaped.fin", options).unwrap();
+    writer.write_all(b"pwned").unwrap();
+    writer.finish().unwrap();
+
+    let app = init_app(temp_home.path(), temp.path());
+    finn(temp_home.path(), &app)
+        .arg("add").arg(file_url(&archive))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("path escapes the archive"));
+
+    assert!(!temp_home.path().join(".finn/escaped.fin").exists());
+    assert!(!temp_home.path().join(".finn/cache/escaped.fin").exists());
+}
+
+#[test]
+fn test_tar_symlink_escape_is_rejected() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let tar = tar_bytes(|builder| {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_cksum();
+        builder.append_link(&mut header, "json-1.2.0/secrets", "../../../../etc").unwrap();
+    });
+    let archive = write_tar_gz(temp.path(), "json-1.2.0.tar.gz", &tar);
+
+    let app = init_app(temp_home.path(), temp.path());
+    finn(temp_home.path(), &app)
+        .arg("add").arg(file_url(&archive))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("escapes the archive"));
+    assert!(!app.join(".finn/packages/json").exists());
+}
@@ -0,0 +1,129 @@
+use predicates::prelude::*;
+use tempfile::TempDir;
+use std::fs;
+use std::path::Path;
+
+mod common;
+use common::finn;
+
+fn setup_project(root: &Path, extra_config: &str) {
+    let config = format!(r#"
+[project]
+name = "audit-app"
+version = "0.1.0"
+envpath = ".finn"
+entrypoint = "main.fin"
+
+[packages]
+fin-http = "https://example.com/fin-http.git@v0.2.0"
+fin-json = "https://example.com/fin-json.git@v1.4.0"
+{}"#, extra_config);
+    fs::write(root.join("finn.toml"), config).unwrap();
+
+    let lock = r#"
+[packages.fin-http]
+version = "v0.2.0"
+source = "https://example.com/fin-http.git"
+commit = "1111111111111111111111111111111111111111"
+checksum = ""
+
+[packages.fin-json]
+version = "v1.4.0"
+source = "https://example.com/fin-json.git"
+commit = "2222222222222222222222222222222222222222"
+checksum = ""
+"#;
+    fs::write(root.join("finn.lock"), lock).unwrap();
+}
+
+fn setup_advisories(root: &Path) -> String {
+    let db = root.join("advisory-db");
+    fs::create_dir_all(db.join("fin-http")).unwrap();
+    fs::create_dir_all(db.join("fin-json")).unwrap();
+
+    fs::write(db.join("fin-http/FINN-2025-0001.toml"), r#"
+[advisory]
+id = "FINN-2025-0001"
+package = "fin-http"
+title = "Header injection in request builder"
+severity = "high"
+url = "https://example.com/advisories/FINN-2025-0001"
+
+[versions]
+affected = [">=0.1.0, <0.3.2"]
+patched = [">=0.3.2"]
+"#).unwrap();
+
+    // fin-json 1.4.0 is already patched
+    fs::write(db.join("fin-json/FINN-2025-0002.toml"), r#"
+[advisory]
+id = "FINN-2025-0002"
+package = "fin-json"
+title = "Stack overflow on deeply nested input"
+severity = "medium"
+
+[versions]
+patched = [">=1.2.0"]
+"#).unwrap();
+
+    db.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_audit_reports_vulnerable_package() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    setup_project(temp.path(), "");
+    let db = setup_advisories(temp.path());
+
+    finn(&home, temp.path())
+        .arg("audit").arg("--db").arg(&db)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("FINN-2025-0001"))
+        .stdout(predicate::str::contains("Patched: >=0.3.2"))
+        .stdout(predicate::str::contains("FINN-2025-0002").not())
+        .stderr(predicate::str::contains("Found 1 vulnerabilities in 1 packages"));
+}
+
+#[test]
+fn test_audit_respects_ignore_list() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let db = setup_advisories(temp.path());
+    setup_project(temp.path(), &format!(r#"
+[audit]
+database = "{}"
+ignore = ["FINN-2025-0001"]
+"#, db.replace("\\", "/")));
+
+    finn(&home, temp.path())
+        .arg("audit")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Ignored advisories: FINN-2025-0001"))
+        .stdout(predicate::str::contains("No known vulnerabilities found"));
+}
+
+#[test]
+fn test_audit_json_output() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    setup_project(temp.path(), "");
+    let db = setup_advisories(temp.path());
+
+    let output = finn(&home, temp.path())
+        .arg("audit").arg("--db").arg(&db).arg("--json")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success(), "Audit should fail when vulnerabilities are found");
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let vulns = report["vulnerabilities"].as_array().unwrap();
+    assert_eq!(vulns.len(), 1);
+    assert_eq!(vulns[0]["package"], "fin-http");
+    assert_eq!(vulns[0]["id"], "FINN-2025-0001");
+    assert_eq!(vulns[0]["severity"], "high");
+    assert_eq!(vulns[0]["patched"][0], ">=0.3.2");
+}
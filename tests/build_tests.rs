@@ -1,11 +1,14 @@
-use assert_cmd::Command;
 use predicates::prelude::*;
 use tempfile::TempDir;
 use std::fs;
 
+mod common;
+use common::{finn, init_project};
+
 #[test]
 fn test_build_invokes_compiler() {
     let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
     let project_path = temp.path().join("BuildProj");
 
     // 1. Create Mock Compiler
@@ -17,14 +20,11 @@ print(f"Mock Compiler Running on {sys.argv[1]}")
     fs::write(&compiler_path, mock_code).unwrap();
 
     // 2. Init Project
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .arg("init").arg(project_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(&home, &project_path);
 
     // 3. Run Build with Mock Compiler
     // FIX: Use "FIN_COMPILER_PATH" (One N) to match src/utils.rs
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&project_path)
+    finn(&home, &project_path)
         .env("FIN_COMPILER_PATH", compiler_path.to_str().unwrap())
         .arg("build")
         .assert()
@@ -35,6 +35,7 @@ print(f"Mock Compiler Running on {sys.argv[1]}")
 #[test]
 fn test_run_passes_flags() {
     let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
     let project_path = temp.path().join("RunProj");
 
     // 1. Create Mock Compiler that checks for flags
@@ -49,14 +50,11 @@ if "-r" in sys.argv:
     fs::write(&compiler_path, mock_code).unwrap();
 
     // 2. Init
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .arg("init").arg(project_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(&home, &project_path);
 
     // 3. Finn Run
     // FIX: Use "FIN_COMPILER_PATH" (One N)
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&project_path)
+    finn(&home, &project_path)
         .env("FIN_COMPILER_PATH", compiler_path.to_str().unwrap())
         .arg("run")
         .arg("--")
@@ -1,17 +1,19 @@
-use assert_cmd::Command;
 use predicates::prelude::*;
 use tempfile::TempDir;
 use std::fs;
 use std::process::Command as SysCommand;
 
+mod common;
+use common::{create_pkg, finn, git_in, init_project};
+
 fn setup_fake_remote(temp: &TempDir) -> String {
     let remote_path = temp.path().join("remote-pkg");
     fs::create_dir(&remote_path).unwrap();
     
     // Initialize a dummy git repo
-    SysCommand::new("git").arg("init").current_dir(&remote_path).output().unwrap();
-    SysCommand::new("git").arg("config").arg("user.email").arg("test@test.com").current_dir(&remote_path).output().unwrap();
-    SysCommand::new("git").arg("config").arg("user.name").arg("Test").current_dir(&remote_path).output().unwrap();
+    git_in(&remote_path, &["init", "-q"]);
+    git_in(&remote_path, &["config", "user.email", "test@test.com"]);
+    git_in(&remote_path, &["config", "user.name", "Test"]);
     
     // Write a VALID finn.toml
     let config = r#"
@@ -24,8 +26,8 @@ entrypoint = "lib.fin"
     fs::write(remote_path.join("finn.toml"), config).unwrap();
     fs::write(remote_path.join("lib.fin"), "pub fun test() {}").unwrap();
     
-    SysCommand::new("git").arg("add").arg(".").current_dir(&remote_path).output().unwrap();
-    SysCommand::new("git").arg("commit").arg("-m").arg("init").current_dir(&remote_path).output().unwrap();
+    git_in(&remote_path, &["add", "."]);
+    git_in(&remote_path, &["commit", "-q", "-m", "init"]);
 
     remote_path.to_str().unwrap().to_string()
 }
@@ -39,18 +41,11 @@ fn test_add_uses_cache() {
     let remote_url = setup_fake_remote(&temp_remote);
     let project_path = temp_project.path();
 
-    let env_vars = vec![("FINN_TEST_HOME", temp_home.path().to_str().unwrap())];
-
     // Initialize
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .envs(env_vars.clone())
-        .arg("init").arg(project_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(temp_home.path(), project_path);
 
     // Add Package
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .envs(env_vars.clone())
-        .current_dir(project_path)
+    finn(temp_home.path(), project_path)
         .arg("add")
         .arg(&remote_url)
         .assert()
@@ -74,9 +69,7 @@ fn test_add_uses_cache() {
     // Re-add (Cache Hit)
     fs::remove_dir_all(&installed_pkg).unwrap();
     
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .envs(env_vars)
-        .current_dir(project_path)
+    finn(temp_home.path(), project_path)
         .arg("add")
         .arg(&remote_url)
         .assert()
@@ -84,3 +77,608 @@ fn test_add_uses_cache() {
     
     assert!(installed_pkg.exists(), "Package should be restored from cache");
 }
+
+fn add(home: &std::path::Path, project: &std::path::Path, source: impl AsRef<std::ffi::OsStr>) {
+    finn(home, project).arg("add").arg(source).assert().success();
+}
+
+#[test]
+fn test_cache_list_verify_and_clean() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let pkg = create_pkg(temp.path(), "listed-pkg", "");
+
+    init_project(temp_home.path(), &project);
+    add(temp_home.path(), &project, &pkg);
+
+    finn(temp_home.path(), &project)
+        .arg("cache").arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("listed-pkg-"))
+        .stdout(predicate::str::contains(pkg.to_str().unwrap()));
+
+    finn(temp_home.path(), &project)
+        .arg("cache").arg("verify")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Verified 1 cache entries"));
+
+    // Tamper with the cached copy
+    let cache_dir = temp_home.path().join(".finn/cache/registry");
+    let entry = fs::read_dir(&cache_dir).unwrap().next().unwrap().unwrap().path();
+    fs::write(entry.join("lib.fin"), "pub fun evil() {}").unwrap();
+
+    finn(temp_home.path(), &project)
+        .arg("cache").arg("verify")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("contents changed since last use"));
+
+    finn(temp_home.path(), &project)
+        .arg("cache").arg("clean")
+        .assert()
+        .success();
+    assert!(!cache_dir.exists() || fs::read_dir(&cache_dir).unwrap().count() == 0);
+}
+
+#[test]
+fn test_cache_prune_older_than() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let old_pkg = create_pkg(temp.path(), "old-pkg", "");
+    let new_pkg = create_pkg(temp.path(), "new-pkg", "");
+
+    init_project(temp_home.path(), &project);
+    add(temp_home.path(), &project, &old_pkg);
+    add(temp_home.path(), &project, &new_pkg);
+
+    // Backdate the first entry by 60 days
+    let index_path = temp_home.path().join(".finn/cache/index.json");
+    let mut index: serde_json::Value = serde_json::from_str(&fs::read_to_string(&index_path).unwrap()).unwrap();
+    for (_, entry) in index["entries"].as_object_mut().unwrap() {
+        if entry["name"] == "old-pkg" {
+            entry["last_used"] = serde_json::json!(entry["last_used"].as_u64().unwrap() - 60 * 86_400);
+        }
+    }
+    fs::write(&index_path, serde_json::to_string(&index).unwrap()).unwrap();
+
+    finn(temp_home.path(), &project)
+        .arg("cache").arg("prune").arg("--older-than").arg("30d")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Pruned 1 entries"));
+
+    let remaining: Vec<String> = fs::read_dir(temp_home.path().join(".finn/cache/registry")).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(remaining.len(), 1);
+    assert!(remaining[0].starts_with("new-pkg-"));
+}
+
+#[test]
+fn test_cache_size_limit_evicts_lru() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let first = create_pkg(temp.path(), "first-pkg", "");
+    let second = create_pkg(temp.path(), "second-pkg", "");
+
+    fs::create_dir_all(temp_home.path().join(".finn")).unwrap();
+    fs::write(temp_home.path().join(".finn/config.toml"), "[cache]\nmax-size = \"1B\"\n").unwrap();
+
+    init_project(temp_home.path(), &project);
+    add(temp_home.path(), &project, &first);
+    add(temp_home.path(), &project, &second);
+
+    // Only the most recently used entry survives a 1 byte cap
+    let remaining: Vec<String> = fs::read_dir(temp_home.path().join(".finn/cache/registry")).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(remaining.len(), 1);
+    assert!(remaining[0].starts_with("second-pkg-"));
+}
+
+fn commit_lib(remote: &std::path::Path, body: &str) {
+    fs::write(remote.join("lib.fin"), body).unwrap();
+    git_in(remote, &["add", "."]);
+    git_in(remote, &["commit", "-q", "-m", body]);
+}
+
+/// A git repository on branch `main`, reachable through a file:// URL (so it is cloned, not copied).
+fn setup_branch_remote(temp: &TempDir) -> (std::path::PathBuf, String) {
+    let remote = temp.path().join("branch-pkg");
+    fs::create_dir(&remote).unwrap();
+    git_in(&remote, &["init", "-q", "-b", "main"]);
+    git_in(&remote, &["config", "user.email", "test@test.com"]);
+    git_in(&remote, &["config", "user.name", "Test"]);
+    fs::write(remote.join("finn.toml"), r#"
+[project]
+name = "branch-pkg"
+version = "0.1.0"
+envpath = ".finn"
+entrypoint = "lib.fin"
+"#).unwrap();
+    commit_lib(&remote, "v1");
+
+    let url = format!("file://{}", remote.to_str().unwrap().replace("\\", "/"));
+    (remote, url)
+}
+
+#[test]
+fn test_branch_dependency_refresh_and_offline() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let (remote, url) = setup_branch_remote(&temp);
+    let spec = format!("{}@main", url);
+    let installed = project.join(".finn/packages/branch-pkg/lib.fin");
+
+    init_project(temp_home.path(), &project);
+    add(temp_home.path(), &project, &spec);
+    assert_eq!(fs::read_to_string(&installed).unwrap(), "v1");
+
+    // Branch moved: re-adding fetches instead of trusting the stale clone
+    commit_lib(&remote, "v2");
+    add(temp_home.path(), &project, &spec);
+    assert_eq!(fs::read_to_string(&installed).unwrap(), "v2");
+
+    // --offline never fetches
+    commit_lib(&remote, "v3");
+    finn(temp_home.path(), &project)
+        .arg("--offline").arg("add").arg(&spec)
+        .assert().success();
+    assert_eq!(fs::read_to_string(&installed).unwrap(), "v2");
+
+    // sync installs the locked commit, even with --refresh
+    finn(temp_home.path(), &project)
+        .arg("--refresh").arg("sync")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Integrity verified"));
+    assert_eq!(fs::read_to_string(&installed).unwrap(), "v2");
+}
+
+#[test]
+fn test_offline_fails_for_uncached_source() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let (_remote, url) = setup_branch_remote(&temp);
+
+    init_project(temp_home.path(), &project);
+    finn(temp_home.path(), &project)
+        .arg("--offline").arg("add").arg(&url)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not in the cache and --offline was given"));
+}
+
+#[test]
+fn test_concurrent_syncs_share_cache() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let (_remote, url) = setup_branch_remote(&temp);
+
+    let projects: Vec<_> = (0..4).map(|i| temp.path().join(format!("App{}", i))).collect();
+    for project in &projects {
+        init_project(temp_home.path(), project);
+        let config = fs::read_to_string(project.join("finn.toml")).unwrap()
+            .replace("[packages]", &format!("[packages]\nbranch-pkg = \"{}@main\"", url));
+        fs::write(project.join("finn.toml"), config).unwrap();
+    }
+
+    let children: Vec<_> = projects.iter().map(|project| {
+        SysCommand::new(assert_cmd::cargo::cargo_bin!("finn"))
+            .env("FINN_TEST_HOME", temp_home.path())
+            .current_dir(project)
+            .arg("sync")
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap()
+    }).collect();
+
+    for child in children {
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "sync failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    for project in &projects {
+        assert_eq!(fs::read_to_string(project.join(".finn/packages/branch-pkg/lib.fin")).unwrap(), "v1");
+    }
+}
+
+#[test]
+fn test_env_lock_times_out() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    init_project(temp_home.path(), &project);
+
+    // Simulate another finn process holding the environment
+    let held = fs::OpenOptions::new().create(true).truncate(false).write(true)
+        .open(project.join(".finn/.lock")).unwrap();
+    held.lock().unwrap();
+
+    finn(temp_home.path(), &project)
+        .env("FINN_LOCK_TIMEOUT", "1")
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Blocking: waiting for file lock on project environment"))
+        .stderr(predicate::str::contains("Timed out after 1s"));
+
+    held.unlock().unwrap();
+    finn(temp_home.path(), &project)
+        .arg("sync")
+        .assert()
+        .success();
+}
+
+#[cfg(unix)]
+fn inode(path: &std::path::Path) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).unwrap().ino()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_projects_share_store_via_hardlinks() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let lib = create_pkg(temp.path(), "SharedLib", "");
+
+    let (app_a, app_b) = (temp.path().join("AppA"), temp.path().join("AppB"));
+    for app in [&app_a, &app_b] {
+        init_project(temp_home.path(), app);
+        add(temp_home.path(), app, &lib);
+    }
+
+    let file_a = app_a.join(".finn/packages/SharedLib/lib.fin");
+    let file_b = app_b.join(".finn/packages/SharedLib/lib.fin");
+    assert_eq!(inode(&file_a), inode(&file_b));
+    assert_eq!(fs::read_dir(temp_home.path().join(".finn/store")).unwrap().count(), 1);
+
+    // Editing a linked file in place is caught by cache verify
+    fs::write(&file_a, "pub fun patched() {}").unwrap();
+    finn(temp_home.path(), &app_a)
+        .arg("cache").arg("verify")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("contents no longer match the checksum"));
+}
+
+#[test]
+fn test_store_lock_is_named_after_the_entry() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let lib = create_pkg(temp.path(), "LockedLib", "");
+
+    let project = temp.path().join("App");
+    init_project(temp_home.path(), &project);
+    add(temp_home.path(), &project, &lib);
+
+    // The store entry and its lock drop the ':' of the checksum, which Windows rejects in file names
+    let entry = fs::read_dir(temp_home.path().join(".finn/store")).unwrap()
+        .next().unwrap().unwrap().file_name().into_string().unwrap();
+    assert!(entry.starts_with("sha256-v2-"), "unexpected store entry {}", entry);
+    assert!(temp_home.path().join(".finn/cache/locks").join(format!("store-{}.lock", entry)).exists());
+    for lock in fs::read_dir(temp_home.path().join(".finn/cache/locks")).unwrap() {
+        assert!(!lock.unwrap().file_name().to_string_lossy().contains(':'));
+    }
+}
+
+#[test]
+fn test_cache_clean_skips_store_entries_in_use() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let lib = create_pkg(temp.path(), "BusyLib", "");
+
+    let project = temp.path().join("App");
+    init_project(temp_home.path(), &project);
+    add(temp_home.path(), &project, &lib);
+
+    // Held by another finn process installing from the entry
+    let store = temp_home.path().join(".finn/store");
+    let entry = fs::read_dir(&store).unwrap().next().unwrap().unwrap().file_name().into_string().unwrap();
+    let lock = fs::File::open(temp_home.path().join(".finn/cache/locks").join(format!("store-{}.lock", entry))).unwrap();
+    lock.try_lock().unwrap();
+
+    finn(temp_home.path(), &project)
+        .arg("cache").arg("clean")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("store/{}: in use by another finn process, skipped", entry)));
+    assert!(store.join(&entry).exists());
+
+    drop(lock);
+    finn(temp_home.path(), &project).arg("cache").arg("clean").assert().success();
+    assert!(!store.join(&entry).exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_store_copy_link_mode() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let lib = create_pkg(temp.path(), "CopiedLib", "");
+
+    fs::create_dir_all(temp_home.path().join(".finn")).unwrap();
+    fs::write(temp_home.path().join(".finn/config.toml"), "[store]\nlink-mode = \"copy\"\n").unwrap();
+
+    let project = temp.path().join("App");
+    init_project(temp_home.path(), &project);
+    add(temp_home.path(), &project, &lib);
+
+    let store_entry = fs::read_dir(temp_home.path().join(".finn/store")).unwrap()
+        .next().unwrap().unwrap().path();
+    let installed = project.join(".finn/packages/CopiedLib/lib.fin");
+    assert_ne!(inode(&installed), inode(&store_entry.join("lib.fin")));
+    assert_eq!(fs::read_to_string(installed).unwrap(), "pub fun test() {}");
+}
+
+fn cache_entry_for(home: &std::path::Path, reference: &str) -> (std::path::PathBuf, serde_json::Value) {
+    let index: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(home.join(".finn/cache/index.json")).unwrap()
+    ).unwrap();
+    let (key, entry) = index["entries"].as_object().unwrap().iter()
+        .find(|(_, e)| e["ref"] == reference)
+        .unwrap();
+    (home.join(".finn/cache/registry").join(key), entry.clone())
+}
+
+fn history_length(repo: &std::path::Path) -> String {
+    let output = SysCommand::new("git").args(["rev-list", "--count", "HEAD"]).current_dir(repo).output().unwrap();
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Serves the repositories below `root` over git://, which (unlike local paths) supports shallow fetches.
+struct GitDaemon {
+    child: std::process::Child,
+    port: u16,
+}
+
+impl GitDaemon {
+    fn start(root: &std::path::Path) -> Self {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        // Run git-daemon itself: killing a `git daemon` wrapper would leave the daemon running,
+        // still holding the test's stdout
+        let exec_path = SysCommand::new("git").arg("--exec-path").output().unwrap();
+        let exec_path = String::from_utf8_lossy(&exec_path.stdout).trim().to_string();
+        let child = SysCommand::new(std::path::Path::new(&exec_path).join("git-daemon"))
+            .arg("--export-all").arg("--reuseaddr").arg("--listen=127.0.0.1")
+            .arg(format!("--port={}", port))
+            .arg(format!("--base-path={}", root.display()))
+            .arg(root)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        for _ in 0..100 {
+            if std::net::TcpStream::connect(("127.0.0.1", port)).is_ok() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        GitDaemon { child, port }
+    }
+
+    fn url(&self, repo: &str) -> String {
+        format!("git://127.0.0.1:{}/{}", self.port, repo)
+    }
+}
+
+impl Drop for GitDaemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn test_shallow_fetch_and_full_fallback() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let (remote, _) = setup_branch_remote(&temp);
+    commit_lib(&remote, "v2");
+    git_in(&remote, &["tag", "v2.0.0"]);
+    commit_lib(&remote, "v3");
+    let daemon = GitDaemon::start(temp.path());
+    let url = daemon.url("branch-pkg");
+
+    init_project(temp_home.path(), &project);
+
+    // A tag is fetched on its own, without history
+    add(temp_home.path(), &project, format!("{}@v2.0.0", url));
+    let (cached, entry) = cache_entry_for(temp_home.path(), "v2.0.0");
+    assert_eq!(entry["shallow"], true);
+    assert_eq!(history_length(&cached), "1");
+    assert_eq!(fs::read_to_string(project.join(".finn/packages/branch-pkg/lib.fin")).unwrap(), "v2");
+
+    // Abbreviated commits cannot be fetched directly, so the full history is downloaded
+    let first = SysCommand::new("git").args(["rev-list", "--max-parents=0", "HEAD"]).current_dir(&remote).output().unwrap();
+    let short = String::from_utf8_lossy(&first.stdout)[..8].to_string();
+    add(temp_home.path(), &project, format!("{}@{}", url, short));
+    let (cached, entry) = cache_entry_for(temp_home.path(), &short);
+    assert_eq!(entry["shallow"], false);
+    assert_eq!(history_length(&cached), "1");
+    assert_eq!(fs::read_to_string(project.join(".finn/packages/branch-pkg/lib.fin")).unwrap(), "v1");
+
+    finn(temp_home.path(), &project)
+        .arg("cache").arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("shallow"))
+        .stdout(predicate::str::contains("full"));
+}
+
+#[test]
+fn test_git_errors_are_actionable() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let (_remote, url) = setup_branch_remote(&temp);
+    init_project(temp_home.path(), &project);
+
+    let missing = format!("file://{}", temp.path().join("no-such-repo").to_str().unwrap().replace("\\", "/"));
+    finn(temp_home.path(), &project)
+        .arg("add").arg(&missing)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("was not found or is not accessible"));
+
+    finn(temp_home.path(), &project)
+        .arg("add").arg(format!("{}@no-such-tag", url))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Reference 'no-such-tag' was not found"));
+}
+
+#[test]
+fn test_source_replace_fetches_from_mirror() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let (remote, _) = setup_branch_remote(&temp);
+    let mirror = temp.path().join("mirror/acme");
+    fs::create_dir_all(&mirror).unwrap();
+    git_in(&mirror, &["clone", "-q", "--bare", remote.to_str().unwrap(), "branch-pkg.git"]);
+    let mirror_base = format!("file://{}/", mirror.to_str().unwrap().replace("\\", "/"));
+
+    fs::create_dir_all(temp_home.path().join(".finn")).unwrap();
+    fs::write(
+        temp_home.path().join(".finn/config.toml"),
+        format!("[source-replace]\n\"https://github.com/\" = \"https://unreachable.invalid/\"\n\"https://github.com/acme/\" = \"{}\"\n", mirror_base),
+    ).unwrap();
+
+    init_project(temp_home.path(), &project);
+    add(temp_home.path(), &project, "acme/branch-pkg");
+    assert_eq!(fs::read_to_string(project.join(".finn/packages/branch-pkg/lib.fin")).unwrap(), "v1");
+
+    // Manifest and lockfile keep the original URL
+    let original = "https://github.com/acme/branch-pkg.git";
+    let lock = fs::read_to_string(project.join("finn.lock")).unwrap();
+    assert!(lock.contains(&format!("source = \"{}\"", original)), "{}", lock);
+    assert!(!lock.contains(&mirror_base), "{}", lock);
+
+    fs::remove_dir_all(project.join(".finn/packages/branch-pkg")).unwrap();
+    finn(temp_home.path(), &project)
+        .arg("--refresh").arg("sync")
+        .assert()
+        .success();
+    assert!(project.join(".finn/packages/branch-pkg/lib.fin").exists());
+}
+
+#[test]
+fn test_fetch_then_offline_sync() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let (_remote, url) = setup_branch_remote(&temp);
+    let unlocked = create_pkg(temp.path(), "unlocked-pkg", "");
+
+    let mut server = mockito::Server::new();
+    let _m = server.mock("GET", "/api/packages/registry-pkg")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(format!(r#"{{ "name": "registry-pkg", "repo_url": "{}", "latest_version": "main" }}"#, url))
+        .create();
+
+    init_project(temp_home.path(), &project);
+    finn(temp_home.path(), &project)
+        .env("FINN_REGISTRY_URL", server.url())
+        .arg("add").arg("registry-pkg").arg("--allow-confusable")
+        .assert()
+        .success();
+
+    // A fresh machine: empty home, nothing installed, plus an entry that is not locked yet
+    let fresh_home = TempDir::new().unwrap();
+    fs::remove_dir_all(project.join(".finn/packages")).unwrap();
+    let config = fs::read_to_string(project.join("finn.toml")).unwrap();
+    fs::write(project.join("finn.toml"), config.replace("[packages]", &format!("[packages]\nunlocked-pkg = {:?}", unlocked))).unwrap();
+    let lock_before = fs::read_to_string(project.join("finn.lock")).unwrap();
+
+    finn(fresh_home.path(), &project)
+        .env("FINN_REGISTRY_URL", server.url())
+        .arg("fetch")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fetched 2 packages"));
+
+    assert!(!project.join(".finn/packages/registry-pkg").exists());
+    assert_eq!(fs::read_to_string(project.join("finn.lock")).unwrap(), lock_before);
+
+    // Offline sync needs neither the registry nor the git remote
+    fs::remove_dir_all(temp.path().join("branch-pkg")).unwrap();
+    finn(fresh_home.path(), &project)
+        .env("FINN_REGISTRY_URL", "http://127.0.0.1:9")
+        .arg("--offline").arg("sync")
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(project.join(".finn/packages/registry-pkg/lib.fin")).unwrap(), "v1");
+    assert!(project.join(".finn/packages/unlocked-pkg/lib.fin").exists());
+}
+
+#[test]
+fn test_bundle_moves_cache_to_another_machine() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let project = temp.path().join("App");
+    let (_remote, url) = setup_branch_remote(&temp);
+    let local = create_pkg(temp.path(), "local-pkg", "");
+
+    init_project(temp_home.path(), &project);
+    add(temp_home.path(), &project, format!("{}@main", url));
+    add(temp_home.path(), &project, &local);
+
+    finn(temp_home.path(), &project)
+        .arg("bundle").arg("create").arg(temp.path().join("deps.finnbundle").to_str().unwrap())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Bundled 2 packages"));
+
+    // The isolated machine has neither the sources nor a cache
+    fs::remove_dir_all(temp.path().join("branch-pkg")).unwrap();
+    fs::remove_dir_all(&local).unwrap();
+    fs::remove_dir_all(project.join(".finn/packages")).unwrap();
+    let isolated_home = TempDir::new().unwrap();
+
+    let import = || {
+        finn(isolated_home.path(), &project)
+            .arg("bundle").arg("import").arg(temp.path().join("deps.finnbundle").to_str().unwrap())
+            .assert()
+            .success()
+    };
+    import().stdout(predicate::str::contains("Imported 2 cache entries"));
+    import().stdout(predicate::str::contains("Imported 0 cache entries").and(predicate::str::contains("2 already cached")));
+
+    finn(isolated_home.path(), &project)
+        .arg("--offline").arg("sync")
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(project.join(".finn/packages/branch-pkg/lib.fin")).unwrap(), "v1");
+    assert!(project.join(".finn/packages/local-pkg/lib.fin").exists());
+
+    finn(isolated_home.path(), &project)
+        .arg("cache").arg("verify")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_bundle_import_rejects_invalid_files() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let bogus = temp.path().join("bogus.finnbundle");
+    fs::write(&bogus, "not a bundle").unwrap();
+
+    finn(temp_home.path(), temp.path())
+        .arg("bundle").arg("import").arg(bogus.to_str().unwrap())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not a valid finn bundle"));
+}
@@ -0,0 +1,200 @@
+use predicates::prelude::*;
+use tempfile::TempDir;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod common;
+use common::{create_pkg, finn, init_app};
+
+/// A library with everything `finn check` asks for.
+fn create_lib(root: &Path, name: &str, extra_config: &str) -> PathBuf {
+    let lib = create_pkg(root, name, &format!(r#"license = "MIT"
+description = "A test library"
+repository = "https://example.com/{}.git"
+{}"#, name, extra_config));
+    fs::create_dir_all(lib.join("src")).unwrap();
+    fs::rename(lib.join("lib.fin"), lib.join("src/lib.fin")).unwrap();
+    fs::write(lib.join("exports.fin"), "// Public API\nexport * from \"src/lib\";\n").unwrap();
+    fs::write(lib.join("README.md"), "# Lib\n").unwrap();
+    lib
+}
+
+#[test]
+fn test_check_clean_package() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let lib = create_lib(temp.path(), "CleanLib", "");
+
+    finn(&home, &lib)
+        .arg("check").arg("--strict")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("CleanLib: no problems found."));
+}
+
+#[test]
+fn test_check_reports_errors() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let lib = create_lib(temp.path(), "BrokenLib", "");
+    fs::write(lib.join("exports.fin"), "export * from \"src/missing\";\nfun oops() {}\n").unwrap();
+    fs::remove_file(lib.join("src/lib.fin")).unwrap();
+
+    finn(&home, &lib)
+        .arg("check")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("[FAIL] BrokenLib: Entrypoint 'lib.fin' does not exist"))
+        .stdout(predicate::str::contains("[FAIL] BrokenLib: exports.fin:1: module 'src/missing' does not exist."))
+        .stdout(predicate::str::contains("[WARN] BrokenLib: exports.fin:2: expected an `export` statement"))
+        .stderr(predicate::str::contains("'BrokenLib' is not ready to publish: 2 errors and 1 warnings."));
+}
+
+#[test]
+fn test_check_requires_publish_metadata() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let lib = temp.path().join("bad.name.");
+    fs::create_dir_all(&lib).unwrap();
+    fs::write(lib.join("finn.toml"), r#"
+[project]
+name = "bad.name."
+version = "one"
+envpath = ".finn"
+repository = "example.com/repo"
+"#).unwrap();
+    fs::write(lib.join("README.md"), "# Bad\n").unwrap();
+
+    // Every problem is reported, not just the first
+    finn(&home, &lib)
+        .arg("check")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("[FAIL] bad.name.: Project name: Invalid package name"))
+        .stdout(predicate::str::contains("[FAIL] bad.name.: Version 'one' is not a semantic version"))
+        .stdout(predicate::str::contains("[FAIL] bad.name.: No description"))
+        .stdout(predicate::str::contains("[FAIL] bad.name.: Repository 'example.com/repo' is not a URL."))
+        .stdout(predicate::str::contains("[FAIL] bad.name.: No entrypoint"))
+        .stdout(predicate::str::contains("[FAIL] bad.name.: No license declared"))
+        .stderr(predicate::str::contains("'bad.name.' is not ready to publish: 6 errors and 0 warnings."));
+}
+
+#[test]
+fn test_check_rejects_unpinned_dependencies() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    create_lib(temp.path(), "Sibling", "");
+    let lib = create_lib(temp.path(), "DepLib", r#"
+[packages]
+Sibling = "../Sibling"
+Nightly = "user/nightly@main"
+Tip = "https://example.com/tip.git"
+Detailed = { git = "https://example.com/detailed.git", version = "develop" }
+Tagged = "user/tagged@v1.2.0"
+Series = "user/series@1.x"
+Commit = { git = "https://example.com/commit.git", version = "0123abcd0123abcd0123abcd0123abcd0123abcd" }
+Short = { git = "https://example.com/short.git", version = "0123abcd" }
+Registry = "json@^1.0"
+Archive = { url = "https://example.com/a-1.0.tar.gz", sha256 = "00" }
+"#);
+
+    finn(&home, &lib)
+        .arg("check")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Dependency 'Sibling' is a local path ('../Sibling')"))
+        .stdout(predicate::str::contains("Dependency 'Nightly' follows the git branch 'main'"))
+        .stdout(predicate::str::contains("Dependency 'Tip' follows the default branch of 'https://example.com/tip.git'"))
+        .stdout(predicate::str::contains("Dependency 'Detailed' follows the git branch 'develop'"))
+        .stdout(predicate::str::contains("Dependency 'Series' follows the git branch '1.x'"))
+        .stdout(predicate::str::contains("Dependency 'Short' refers to the commit '0123abcd' by an abbreviated id"))
+        .stdout(predicate::str::contains("'Tagged'").not())
+        .stdout(predicate::str::contains("'Commit'").not())
+        .stdout(predicate::str::contains("'Registry'").not())
+        .stdout(predicate::str::contains("'Archive'").not())
+        .stderr(predicate::str::contains("'DepLib' is not ready to publish: 6 errors and 0 warnings."));
+}
+
+#[test]
+fn test_check_resolves_refs_in_the_cache() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let tools = create_pkg(temp.path(), "tools", "");
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git").args(args).current_dir(&tools).status().unwrap();
+        assert!(status.success(), "git {:?}", args);
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.email", "test@test.com"]);
+    git(&["config", "user.name", "Test"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "init"]);
+    git(&["tag", "release-2024"]);
+    git(&["branch", "1.x"]);
+
+    // Served from a local mirror, so the dependencies keep a URL users can fetch
+    fs::create_dir_all(home.join(".finn")).unwrap();
+    fs::write(home.join(".finn/config.toml"), format!(
+        "[source-replace]\n\"https://example.com/acme/\" = \"file://{}/\"\n",
+        temp.path().to_str().unwrap().replace("\\", "/"),
+    )).unwrap();
+    let lib = create_lib(temp.path(), "CachedLib", "");
+
+    // Not a semantic version, but a tag of the cached repository
+    finn(&home, &lib).arg("add").arg("https://example.com/acme/tools@release-2024").assert().success();
+    finn(&home, &lib).arg("check").assert().success();
+
+    finn(&home, &lib).arg("add").arg("https://example.com/acme/tools@1.x").assert().success();
+    finn(&home, &lib)
+        .arg("check")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Dependency 'tools' follows the git branch '1.x'"));
+}
+
+#[test]
+fn test_check_warns_about_binaries() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let lib = create_lib(temp.path(), "BinLib", "");
+    fs::write(lib.join("tool"), b"\x7fELF\x02\x01\x01\x00").unwrap();
+
+    finn(&home, &lib)
+        .arg("check")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[WARN] BinLib: 'tool' is a compiled binary"))
+        .stdout(predicate::str::contains("passed with 1 warnings"));
+
+    finn(&home, &lib)
+        .arg("check").arg("--strict")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'BinLib' is not ready to publish: 0 errors and 1 warnings."));
+
+    // Ignored files are not part of the package
+    fs::write(lib.join(".finnignore"), "tool\n").unwrap();
+    finn(&home, &lib)
+        .arg("check").arg("--strict")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_add_rejects_invalid_package() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    let lib = create_lib(root, "BadExports", "");
+    fs::write(lib.join("exports.fin"), "export * from \"src/gone\";\n").unwrap();
+
+    let app = init_app(&home, root);
+
+    finn(&home, &app)
+        .arg("add").arg("../BadExports")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Package validation failed"))
+        .stderr(predicate::str::contains("exports.fin:1: module 'src/gone' does not exist."));
+    assert!(!app.join(".finn/packages/BadExports").exists());
+}
@@ -1,15 +1,17 @@
-use assert_cmd::Command;
 use predicates::prelude::*;
 use tempfile::TempDir;
 
+mod common;
+use common::finn;
+
 #[test]
 fn test_init_creates_files() {
     let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
     let path = temp.path().join("my_project");
 
-    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin("finn"));
-    
-    cmd.arg("init")
+    finn(&home, temp.path())
+       .arg("init")
        .arg(path.to_str().unwrap())
        .arg("--yes") // FIX: Skip interactive wizard
        .assert()
@@ -24,16 +26,17 @@ fn test_init_creates_files() {
 #[test]
 fn test_init_idempotency() {
     let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
     let path = temp.path();
 
     // First run
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
+    finn(temp_home.path(), path)
         .arg("init").arg(path.to_str().unwrap())
         .arg("--yes") // FIX: Skip interactive wizard
         .assert().success();
 
     // Second run (should not fail)
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
+    finn(temp_home.path(), path)
         .arg("init").arg(path.to_str().unwrap())
         .arg("--yes") // FIX: Skip interactive wizard
         .assert()
@@ -44,10 +47,9 @@ fn test_init_idempotency() {
 #[test]
 fn test_healthcheck_fails_outside_project() {
     let temp = TempDir::new().unwrap();
-    
-    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin("finn"));
-    
-    cmd.current_dir(temp.path())
+    let home = temp.path().join("home");
+
+    finn(&home, temp.path())
        .arg("healthcheck")
        .assert()
        .failure() 
@@ -0,0 +1,116 @@
+//! Fixtures shared by the integration tests. Each test crate uses a subset of them.
+#![allow(dead_code)]
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `finn` with its home directory redirected to `home`, run in `dir`.
+pub fn finn(home: &Path, dir: &Path) -> Command {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("finn"));
+    cmd.env("FINN_TEST_HOME", home).current_dir(dir);
+    cmd
+}
+
+/// Runs `finn init --yes` for `project`.
+pub fn init_project(home: &Path, project: &Path) {
+    finn(home, project.parent().unwrap())
+        .arg("init").arg(project.to_str().unwrap()).arg("--yes")
+        .assert().success();
+}
+
+/// Creates the application `root/App`.
+pub fn init_app(home: &Path, root: &Path) -> PathBuf {
+    let app = root.join("App");
+    init_project(home, &app);
+    app
+}
+
+/// Creates a local package `root/name` with a `lib.fin` entrypoint. `extra_config` is
+/// appended to its finn.toml, e.g. a `[packages]` table.
+pub fn create_pkg(root: &Path, name: &str, extra_config: &str) -> PathBuf {
+    let path = root.join(name);
+    fs::create_dir_all(&path).unwrap();
+    fs::write(path.join("finn.toml"), format!(r#"
+[project]
+name = "{}"
+version = "0.1.0"
+envpath = ".finn"
+entrypoint = "lib.fin"
+{}"#, name, extra_config)).unwrap();
+    fs::write(path.join("lib.fin"), "pub fun test() {}").unwrap();
+    path
+}
+
+/// Runs git in `dir`, allowing file:// submodules.
+pub fn git_in(dir: &Path, args: &[&str]) {
+    let output = std::process::Command::new("git")
+        .args(["-c", "protocol.file.allow=always"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+}
+
+/// A git repository holding several packages under `libs/`, tagged `v1.0.0`. Returns its file:// URL.
+pub fn create_monorepo(root: &Path) -> String {
+    let repo = root.join("mono");
+    for name in ["json", "http"] {
+        let pkg = repo.join("libs").join(name);
+        fs::create_dir_all(&pkg).unwrap();
+        fs::write(pkg.join("finn.toml"), format!(r#"
+[project]
+name = "{}"
+version = "0.1.0"
+envpath = ".finn"
+entrypoint = "lib.fin"
+"#, name)).unwrap();
+        fs::write(pkg.join("lib.fin"), format!("pub fun {}() {{}}", name)).unwrap();
+    }
+    fs::write(repo.join("README.md"), "monorepo").unwrap();
+
+    for args in [
+        vec!["init", "-q"],
+        vec!["config", "user.email", "test@test.com"],
+        vec!["config", "user.name", "Test"],
+        vec!["add", "."],
+        vec!["commit", "-q", "-m", "init"],
+        vec!["tag", "v1.0.0"],
+    ] {
+        git_in(&repo, &args);
+    }
+    format!("file://{}", repo.to_str().unwrap().replace("\\", "/"))
+}
+
+/// A git package `root/cbind` that vendors the repository `root/native` as a submodule under
+/// `vendor/native`. Returns the package's file:// URL.
+pub fn create_package_with_submodule(root: &Path) -> String {
+    let native = root.join("native");
+    fs::create_dir(&native).unwrap();
+    fs::write(native.join("CMakeLists.txt"), "project(native C)").unwrap();
+    let pkg = root.join("cbind");
+    fs::create_dir(&pkg).unwrap();
+    fs::write(pkg.join("finn.toml"), r#"
+[project]
+name = "cbind"
+version = "0.1.0"
+envpath = ".finn"
+entrypoint = "lib.fin"
+"#).unwrap();
+    fs::write(pkg.join("lib.fin"), "pub fun bind() {}").unwrap();
+
+    for repo in [&native, &pkg] {
+        git_in(repo, &["init", "-q"]);
+        git_in(repo, &["config", "user.email", "test@test.com"]);
+        git_in(repo, &["config", "user.name", "Test"]);
+    }
+    git_in(&native, &["add", "."]);
+    git_in(&native, &["commit", "-q", "-m", "native"]);
+
+    let native_url = format!("file://{}", native.to_str().unwrap().replace("\\", "/"));
+    git_in(&pkg, &["submodule", "add", "-q", &native_url, "vendor/native"]);
+    git_in(&pkg, &["add", "."]);
+    git_in(&pkg, &["commit", "-q", "-m", "init"]);
+    format!("file://{}", pkg.to_str().unwrap().replace("\\", "/"))
+}
@@ -1,65 +1,36 @@
-use assert_cmd::Command;
 use predicates::prelude::*;
 use tempfile::TempDir;
 use std::fs;
 
-// Helper to create a dummy library package
-fn create_dummy_lib(root: &std::path::Path, name: &str, dep: Option<(&str, &str)>) {
-    let lib_path = root.join(name);
-    fs::create_dir(&lib_path).unwrap();
-    
-    let mut config = format!(r#"
-[project]
-name = "{}"
-version = "0.1.0"
-envpath = ".finn"
-entrypoint = "lib.fin"
-
-[packages]
-"#, name);
+mod common;
+use common::{create_monorepo, create_package_with_submodule, create_pkg, finn, git_in, init_project};
 
-    if let Some((dep_name, dep_path)) = dep {
-        // Escape backslashes for Windows paths
-        let clean_path = dep_path.replace("\\", "/");
-        config.push_str(&format!("{} = \"{}\"\n", dep_name, clean_path));
+fn create_lib_with_deps(root: &std::path::Path, name: &str, deps: &[&str]) {
+    let mut extra = String::from("\n[packages]\n");
+    for dep in deps {
+        extra.push_str(&format!("{} = \"{}\"\n", dep, root.join(dep).to_str().unwrap().replace("\\", "/")));
     }
-
-    fs::write(lib_path.join("finn.toml"), config).unwrap();
-    fs::write(lib_path.join("lib.fin"), "pub fun test() {}").unwrap();
-    fs::write(lib_path.join("exports.fin"), "export *").unwrap();
-    
-    // Initialize git for the dummy lib so it can be cloned/added if needed
-    // (Though for local paths, our cache logic might skip git, but good practice)
-    std::process::Command::new("git").arg("init").current_dir(&lib_path).output().unwrap();
-    std::process::Command::new("git").arg("config").arg("user.email").arg("test@test.com").current_dir(&lib_path).output().unwrap();
-    std::process::Command::new("git").arg("config").arg("user.name").arg("Test").current_dir(&lib_path).output().unwrap();
-    std::process::Command::new("git").arg("add").arg(".").current_dir(&lib_path).output().unwrap();
-    std::process::Command::new("git").arg("commit").arg("-m").arg("init").current_dir(&lib_path).output().unwrap();
+    create_pkg(root, name, &extra);
 }
 
 #[test]
 fn test_recursive_add() {
     let temp = TempDir::new().unwrap();
     let root = temp.path();
+    let home = root.join("home");
 
     // 1. Create LibB
-    create_dummy_lib(root, "LibB", None);
+    create_lib_with_deps(root, "LibB", &[]);
 
-    // 2. Create LibA (Depends on LibB)
-    // We use absolute paths or relative to the root to ensure resolution works
-    // Since we are running 'add ../LibA' from App, LibA is at ../LibA.
-    // Inside LibA, LibB is at ../LibB.
-    create_dummy_lib(root, "LibA", Some(("LibB", "../LibB")));
+    // 2. Create LibA (Depends on LibB by its absolute path)
+    create_lib_with_deps(root, "LibA", &["LibB"]);
 
     // 3. Init App
     let app_path = root.join("App");
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .arg("init").arg(app_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(&home, &app_path);
 
     // 4. Add LibA (Should pull LibB recursively)
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&app_path)
+    finn(&home, &app_path)
         .arg("add")
         .arg("../LibA")
         .assert()
@@ -76,25 +47,22 @@ fn test_recursive_add() {
 #[test]
 fn test_remove_package() {
     let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
     let app_path = temp.path().join("App");
     
-    create_dummy_lib(temp.path(), "SimpleLib", None);
+    create_pkg(temp.path(), "SimpleLib", "");
 
     // Init & Add
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .arg("init").arg(app_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(&home, &app_path);
 
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&app_path)
+    finn(&home, &app_path)
         .arg("add").arg("../SimpleLib")
         .assert().success();
 
     assert!(app_path.join(".finn/packages/SimpleLib").exists());
 
     // Remove
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&app_path)
+    finn(&home, &app_path)
         .arg("remove").arg("SimpleLib")
         .assert()
         .success()
@@ -111,15 +79,14 @@ fn test_remove_package() {
 #[test]
 fn test_sync_restores_packages() {
     let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
     let app_path = temp.path().join("App");
     let lib_path = temp.path().join("RestoreLib");
 
-    create_dummy_lib(temp.path(), "RestoreLib", None);
+    create_pkg(temp.path(), "RestoreLib", "");
 
     // Init
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .arg("init").arg(app_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(&home, &app_path);
 
     // FIX: Overwrite finn.toml completely to avoid duplicate [packages] sections
     let config_path = app_path.join("finn.toml");
@@ -141,8 +108,7 @@ RestoreLib = "{}"
     fs::write(&config_path, new_config).unwrap();
 
     // Run Sync
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&app_path)
+    finn(&home, &app_path)
         .arg("sync")
         .assert()
         .success()
@@ -151,3 +117,235 @@ RestoreLib = "{}"
     // Verify it was installed
     assert!(app_path.join(".finn/packages/RestoreLib").exists());
 }
+
+#[test]
+fn test_monorepo_subdir_packages() {
+    let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
+    let app_path = temp.path().join("App");
+    let url = create_monorepo(temp.path());
+
+    init_project(temp_home.path(), &app_path);
+
+    finn(temp_home.path(), &app_path)
+        .arg("add").arg(format!("{}@v1.0.0", url)).arg("--subdir").arg("libs/json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Installed json"));
+
+    let config = fs::read_to_string(app_path.join("finn.toml")).unwrap();
+    assert!(config.contains("subdir = \"libs/json\""), "{}", config);
+
+    // A second package from the same repository, declared by hand
+    fs::write(app_path.join("finn.toml"), format!(
+        "{}\n[packages.http]\ngit = \"{}\"\nversion = \"v1.0.0\"\nsubdir = \"libs/http\"\n", config, url
+    )).unwrap();
+    finn(temp_home.path(), &app_path)
+        .arg("sync")
+        .assert()
+        .success();
+
+    let packages = app_path.join(".finn/packages");
+    assert_eq!(fs::read_to_string(packages.join("json/lib.fin")).unwrap(), "pub fun json() {}");
+    assert_eq!(fs::read_to_string(packages.join("http/lib.fin")).unwrap(), "pub fun http() {}");
+    assert!(!packages.join("json/README.md").exists());
+    assert!(!packages.join("json/libs").exists());
+
+    // Both packages come from one clone
+    let clones = fs::read_dir(temp_home.path().join(".finn/cache/registry")).unwrap().count();
+    assert_eq!(clones, 1);
+
+    let lock = fs::read_to_string(app_path.join("finn.lock")).unwrap();
+    assert!(lock.contains("subdir = \"libs/json\""));
+    assert!(lock.contains("subdir = \"libs/http\""));
+}
+
+#[test]
+fn test_subdir_must_stay_inside_repository() {
+    let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
+    let app_path = temp.path().join("App");
+    let url = create_monorepo(temp.path());
+
+    init_project(temp_home.path(), &app_path);
+
+    finn(temp_home.path(), &app_path)
+        .arg("add").arg(&url).arg("--subdir").arg("../outside")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("must be a relative path inside the repository"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_subdir_cannot_follow_symlinks_out_of_repository() {
+    let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
+    let outside = create_pkg(temp.path(), "outside", "");
+
+    // libs -> / makes every directory on the machine look like part of the repository
+    let repo = temp.path().join("linked");
+    fs::create_dir(&repo).unwrap();
+    std::os::unix::fs::symlink("/", repo.join("libs")).unwrap();
+    fs::write(repo.join("README.md"), "links").unwrap();
+    for args in [
+        vec!["init", "-q"],
+        vec!["config", "user.email", "test@test.com"],
+        vec!["config", "user.name", "Test"],
+        vec!["add", "."],
+        vec!["commit", "-q", "-m", "init"],
+        vec!["tag", "v1.0.0"],
+    ] {
+        git_in(&repo, &args);
+    }
+    let url = format!("file://{}", repo.to_str().unwrap());
+    let subdir = format!("libs{}", outside.to_str().unwrap());
+
+    let app_path = temp.path().join("App");
+    init_project(temp_home.path(), &app_path);
+    finn(temp_home.path(), &app_path)
+        .arg("add").arg(format!("{}@v1.0.0", url)).arg("--subdir").arg(&subdir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!("Invalid subdir '{}'", subdir)))
+        .stderr(predicate::str::contains("outside the repository"));
+    assert!(!app_path.join(".finn/packages/outside").exists());
+}
+
+#[test]
+fn test_submodules_are_opt_in() {
+    let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
+    let url = create_package_with_submodule(temp.path());
+
+    let finn = |dir: &std::path::Path| {
+        let mut cmd = common::finn(temp_home.path(), dir);
+        // Local file:// submodules are blocked by default since git 2.38.1
+        cmd.env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "protocol.file.allow")
+            .env("GIT_CONFIG_VALUE_0", "always");
+        cmd
+    };
+
+    let plain = temp.path().join("Plain");
+    let with_modules = temp.path().join("WithModules");
+    for app in [&plain, &with_modules] {
+        init_project(temp_home.path(), app);
+    }
+
+    finn(&plain).arg("add").arg(&url).assert().success();
+    assert!(!plain.join(".finn/packages/cbind/vendor/native/CMakeLists.txt").exists());
+
+    finn(&with_modules).arg("add").arg(&url).arg("--submodules").assert().success();
+    assert!(with_modules.join(".finn/packages/cbind/vendor/native/CMakeLists.txt").exists());
+
+    let config = fs::read_to_string(with_modules.join("finn.toml")).unwrap();
+    assert!(config.contains("submodules = true"), "{}", config);
+
+    let native_commit = std::process::Command::new("git").args(["rev-parse", "HEAD"])
+        .current_dir(temp.path().join("native")).output().unwrap();
+    let native_commit = String::from_utf8_lossy(&native_commit.stdout).trim().to_string();
+    let lock = fs::read_to_string(with_modules.join("finn.lock")).unwrap();
+    assert!(lock.contains(&format!("\"vendor/native\" = \"{}\"", native_commit)), "{}", lock);
+
+    // The submodule contents are part of the checksum
+    let plain_lock = fs::read_to_string(plain.join("finn.lock")).unwrap();
+    let checksum = |lock: &str| lock.lines().find(|l| l.starts_with("checksum")).unwrap().to_string();
+    assert_ne!(checksum(&lock), checksum(&plain_lock));
+
+    finn(&with_modules).arg("sync").assert().success().stdout(predicate::str::contains("Integrity verified"));
+}
+
+#[test]
+fn test_submodules_use_source_replace_and_policy() {
+    let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
+    let url = create_package_with_submodule(temp.path());
+
+    // The submodule is declared under a host that is only reachable through a mirror
+    let pkg = temp.path().join("cbind");
+    git_in(&pkg, &["config", "-f", ".gitmodules", "submodule.vendor/native.url", "https://git.example.com/acme/native"]);
+    git_in(&pkg, &["commit", "-q", "-am", "move native"]);
+    let mirror = format!("file://{}/", temp.path().to_str().unwrap().replace("\\", "/"));
+    fs::create_dir_all(temp_home.path().join(".finn")).unwrap();
+    fs::write(
+        temp_home.path().join(".finn/config.toml"),
+        format!("[source-replace]\n\"https://git.example.com/acme/\" = \"{}\"\n", mirror),
+    ).unwrap();
+
+    let finn = |dir: &std::path::Path| {
+        let mut cmd = common::finn(temp_home.path(), dir);
+        cmd.env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "protocol.file.allow")
+            .env("GIT_CONFIG_VALUE_0", "always");
+        cmd
+    };
+    let app = temp.path().join("App");
+    init_project(temp_home.path(), &app);
+
+    // Submodule sources are subject to allowed-sources like any other
+    fs::write(app.join("finn-policy.toml"), "allowed-sources = [\"local\"]\n").unwrap();
+    finn(&app).arg("add").arg(&url).arg("--submodules")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("package 'cbind (submodule vendor/native)' comes from 'https://git.example.com/acme/native'"));
+    assert!(!app.join(".finn/packages/cbind").exists());
+
+    fs::write(app.join("finn-policy.toml"), "allowed-sources = [\"local\", \"git.example.com\"]\n").unwrap();
+    finn(&app).arg("add").arg(&url).arg("--submodules").assert().success();
+    assert!(app.join(".finn/packages/cbind/vendor/native/CMakeLists.txt").exists());
+
+    // The mirror is not recorded in the package
+    let gitmodules = fs::read_to_string(app.join(".finn/packages/cbind/.gitmodules")).unwrap();
+    assert!(gitmodules.contains("https://git.example.com/acme/native"), "{}", gitmodules);
+}
+
+#[test]
+fn test_parallel_sync_lockfile_is_deterministic() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    create_lib_with_deps(root, "core", &[]);
+    create_lib_with_deps(root, "util", &["core"]);
+    let libs: Vec<String> = (0..6).map(|i| format!("lib{}", i)).collect();
+    for lib in &libs {
+        create_lib_with_deps(root, lib, &["core", "util"]);
+    }
+
+    let home = root.join("home");
+    let app = root.join("App");
+    init_project(&home, &app);
+    let config = fs::read_to_string(app.join("finn.toml")).unwrap();
+    let deps: String = libs.iter()
+        .map(|lib| format!("{} = \"{}\"\n", lib, root.join(lib).to_str().unwrap().replace("\\", "/")))
+        .collect();
+    fs::write(app.join("finn.toml"), config.replace("[packages]\n", &format!("[packages]\n{}", deps))).unwrap();
+
+    let sync = |jobs: &str| -> String {
+        let home = TempDir::new().unwrap();
+        let _ = fs::remove_dir_all(app.join(".finn"));
+        let _ = fs::remove_file(app.join("finn.lock"));
+        finn(home.path(), &app)
+            .arg("-j").arg(jobs).arg("sync")
+            .assert()
+            .success();
+        for lib in ["core", "util", "lib0", "lib5"] {
+            assert!(app.join(".finn/packages").join(lib).join("lib.fin").exists(), "{} not installed", lib);
+        }
+        fs::read_to_string(app.join("finn.lock")).unwrap()
+    };
+
+    let sequential = sync("1");
+    let parallel = sync("8");
+    assert_eq!(sequential, parallel);
+
+    // Packages are written in name order
+    let core = sequential.find("[packages.core]").unwrap();
+    let lib0 = sequential.find("[packages.lib0]").unwrap();
+    let util = sequential.find("[packages.util]").unwrap();
+    assert!(core < lib0 && lib0 < util, "{}", sequential);
+
+    finn(&home, &app)
+        .arg("-j").arg("0").arg("sync")
+        .assert()
+        .failure();
+}
@@ -1,49 +1,27 @@
-use assert_cmd::Command;
 use predicates::prelude::*;
 use tempfile::TempDir;
 use std::fs;
 
-fn create_dummy_lib(root: &std::path::Path, name: &str) {
-    let lib_path = root.join(name);
-    fs::create_dir(&lib_path).unwrap();
-    
-    let config = format!(r#"
-[project]
-name = "{}"
-version = "0.1.0"
-envpath = ".finn"
-entrypoint = "lib.fin"
-"#, name);
-
-    fs::write(lib_path.join("finn.toml"), config).unwrap();
-    fs::write(lib_path.join("lib.fin"), "pub fun test() {}").unwrap();
-    
-    // Init git so it's a valid source
-    std::process::Command::new("git").arg("init").current_dir(&lib_path).output().unwrap();
-    std::process::Command::new("git").arg("add").arg(".").current_dir(&lib_path).output().unwrap();
-    std::process::Command::new("git").arg("commit").arg("-m").arg("init").current_dir(&lib_path).output().unwrap();
-}
+mod common;
+use common::{create_pkg, finn, init_project};
 
 #[test]
 fn test_integrity_check_passes() {
     let temp = TempDir::new().unwrap();
     let root = temp.path();
-    create_dummy_lib(root, "SafeLib");
+    let home = root.join("home");
+    create_pkg(root, "SafeLib", "");
     
     let app_path = root.join("App");
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .arg("init").arg(app_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(&home, &app_path);
 
     // Add
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&app_path)
+    finn(&home, &app_path)
         .arg("add").arg("../SafeLib")
         .assert().success();
 
     // Sync (Should pass)
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&app_path)
+    finn(&home, &app_path)
         .arg("sync")
         .assert()
         .success()
@@ -54,16 +32,14 @@ fn test_integrity_check_passes() {
 fn test_integrity_check_fails_on_tamper() {
     let temp = TempDir::new().unwrap();
     let root = temp.path();
-    create_dummy_lib(root, "TamperedLib");
+    let home = root.join("home");
+    create_pkg(root, "TamperedLib", "");
     
     let app_path = root.join("App");
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .arg("init").arg(app_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(&home, &app_path);
 
     // 1. Add Package
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&app_path)
+    finn(&home, &app_path)
         .arg("add").arg("../TamperedLib")
         .assert().success();
 
@@ -79,10 +55,266 @@ fn test_integrity_check_fails_on_tamper() {
     fs::write(&lock_path, tampered_lock).unwrap();
 
     // 3. Sync (Should Fail)
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(&app_path)
+    finn(&home, &app_path)
         .arg("sync")
         .assert()
         .failure()
         .stderr(predicate::str::contains("Integrity Check Failed"));
 }
+
+#[test]
+fn test_verify_lists_changed_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    create_pkg(root, "EditedLib", "");
+
+    let app_path = root.join("App");
+    init_project(&home, &app_path);
+
+    finn(&home, &app_path)
+        .arg("add").arg("../EditedLib")
+        .assert().success();
+
+    finn(&home, &app_path)
+        .arg("verify")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Verified 1 packages"));
+
+    // Edit, add and delete files in the installed copy
+    let installed = app_path.join(".finn/packages/EditedLib");
+    fs::remove_file(installed.join("lib.fin")).unwrap();
+    fs::write(installed.join("lib.fin"), "pub fun test() { evil() }").unwrap();
+    fs::write(installed.join("extra.fin"), "").unwrap();
+    fs::remove_file(installed.join("finn.toml")).unwrap();
+
+    finn(&home, &app_path)
+        .arg("verify")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("EditedLib: 3 files differ from finn.lock"))
+        .stdout(predicate::str::contains("modified: lib.fin"))
+        .stdout(predicate::str::contains("added: extra.fin"))
+        .stdout(predicate::str::contains("removed: finn.toml"))
+        .stderr(predicate::str::contains("1 packages failed verification"));
+
+    finn(&home, &app_path)
+        .arg("verify").arg("Missing")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'Missing' is not in finn.lock"));
+
+    // A forced sync puts the locked files back
+    finn(&home, &app_path)
+        .arg("--force").arg("sync")
+        .assert().success();
+
+    finn(&home, &app_path)
+        .arg("verify")
+        .assert()
+        .success();
+}
+
+fn locked_checksum(app_path: &std::path::Path) -> String {
+    let lock = fs::read_to_string(app_path.join("finn.lock")).unwrap();
+    let start = lock.find("checksum = \"").unwrap() + 12;
+    lock[start..].split('"').next().unwrap().to_string()
+}
+
+#[test]
+fn test_legacy_checksums_are_migrated() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    create_pkg(root, "LegacyLib", "");
+
+    let app_path = root.join("App");
+    finn(&home, root).arg("init").arg(app_path.to_str().unwrap()).arg("--yes").assert().success();
+    finn(&home, &app_path).arg("add").arg("../LegacyLib").assert().success();
+
+    // Length-prefixed path, kind and content
+    let v2 = "sha256-v2:fd6c58f40ebdc9c89e67d91c964b0c0e448d332fe6243e56900cc6062b2a0f36";
+    assert_eq!(locked_checksum(&app_path), v2);
+
+    // A lockfile from an older finn: bare SHA-256 over path and content
+    let legacy = "41659d1cc405acb5bc69c7747b9716b23ecf837c71416fefe2dcc696ae2a43e7";
+    let lock_path = app_path.join("finn.lock");
+    fs::write(&lock_path, fs::read_to_string(&lock_path).unwrap().replace(v2, legacy)).unwrap();
+
+    finn(&home, &app_path).arg("verify").assert().success();
+    finn(&home, &app_path)
+        .arg("sync")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Migrated 1 finn.lock checksums to the sha256-v2 format"));
+    assert_eq!(locked_checksum(&app_path), v2);
+
+    // Checksums from a newer finn are rejected rather than misread
+    fs::write(&lock_path, fs::read_to_string(&lock_path).unwrap().replace("sha256-v2:", "sha512-v9:")).unwrap();
+    finn(&home, &app_path)
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unsupported checksum format 'sha512-v9'"));
+}
+
+#[test]
+fn test_blake3_checksums() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    create_pkg(root, "FastLib", "");
+
+    let app_path = root.join("App");
+    finn(&home, root).arg("init").arg(app_path.to_str().unwrap()).arg("--yes").assert().success();
+    let config_path = app_path.join("finn.toml");
+    let config = fs::read_to_string(&config_path).unwrap()
+        .replace("[project]\n", "[project]\nchecksum = \"blake3\"\n");
+    fs::write(&config_path, config).unwrap();
+
+    finn(&home, &app_path).arg("add").arg("../FastLib").assert().success();
+    let checksum = locked_checksum(&app_path);
+    assert!(checksum.starts_with("blake3-v2:"), "{}", checksum);
+    assert_eq!(checksum.len(), "blake3-v2:".len() + 64);
+
+    finn(&home, &app_path).arg("sync").assert().success();
+    finn(&home, &app_path).arg("verify").assert().success();
+
+    // Switching back rewrites the lockfile on the next sync
+    let config = fs::read_to_string(&config_path).unwrap().replace("checksum = \"blake3\"\n", "");
+    fs::write(&config_path, config).unwrap();
+    finn(&home, &app_path)
+        .arg("sync")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Migrated 1 finn.lock checksums to the sha256-v2 format"));
+    assert!(locked_checksum(&app_path).starts_with("sha256-v2:"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinks_are_hashed_but_file_modes_are_not() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    create_pkg(root, "ModeLib", "");
+
+    let app_path = root.join("App");
+    finn(&home, root).arg("init").arg(app_path.to_str().unwrap()).arg("--yes").assert().success();
+    finn(&home, &app_path).arg("add").arg("../ModeLib").assert().success();
+    let lib = root.join("ModeLib");
+    let reinstall = || {
+        finn(&home, &app_path).arg("--force").arg("add").arg("../ModeLib").assert().success();
+        locked_checksum(&app_path)
+    };
+
+    // Windows has no executable bit, so it must not change the checksum
+    fs::write(lib.join("run.sh"), "#!/bin/sh\n").unwrap();
+    let plain = reinstall();
+    fs::set_permissions(lib.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    assert_eq!(reinstall(), plain);
+
+    // Adding a symlink does
+    std::os::unix::fs::symlink("lib.fin", lib.join("alias.fin")).unwrap();
+    assert_ne!(reinstall(), plain);
+
+    // Installed as a link and an executable, not as copies
+    let installed = app_path.join(".finn/packages/ModeLib");
+    assert!(fs::symlink_metadata(installed.join("alias.fin")).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_link(installed.join("alias.fin")).unwrap(), std::path::Path::new("lib.fin"));
+    assert_ne!(fs::metadata(installed.join("run.sh")).unwrap().permissions().mode() & 0o111, 0);
+
+    // A copy without the executable bit, as on Windows, still verifies
+    fs::remove_file(installed.join("run.sh")).unwrap();
+    fs::write(installed.join("run.sh"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(installed.join("run.sh"), fs::Permissions::from_mode(0o644)).unwrap();
+    finn(&home, &app_path).arg("verify").assert().success();
+
+    // Replacing the symlink with a copy of its target does not
+    fs::remove_file(installed.join("alias.fin")).unwrap();
+    fs::copy(installed.join("lib.fin"), installed.join("alias.fin")).unwrap();
+    finn(&home, &app_path)
+        .arg("verify")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("modified: alias.fin"));
+}
+
+#[test]
+fn test_ignored_files_are_not_part_of_the_package() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    create_pkg(root, "TidyLib", "");
+
+    let lib = root.join("TidyLib");
+    let config = fs::read_to_string(lib.join("finn.toml")).unwrap()
+        + "exclude = [\"notes.txt\"]\n";
+    fs::write(lib.join("finn.toml"), config).unwrap();
+    fs::write(lib.join(".finnignore"), "*.swp\nout/\n").unwrap();
+    fs::write(lib.join("notes.txt"), "todo").unwrap();
+    fs::write(lib.join(".lib.fin.swp"), "swap").unwrap();
+    fs::create_dir_all(lib.join("out")).unwrap();
+    fs::write(lib.join("out/lib.o"), "build").unwrap();
+    fs::create_dir_all(lib.join(".finn/packages/Other")).unwrap();
+    fs::write(lib.join(".finn/packages/Other/lib.fin"), "").unwrap();
+
+    let app_path = root.join("App");
+    finn(&home, root).arg("init").arg(app_path.to_str().unwrap()).arg("--yes").assert().success();
+    finn(&home, &app_path).arg("add").arg("../TidyLib").assert().success();
+    let checksum = locked_checksum(&app_path);
+
+    let installed = app_path.join(".finn/packages/TidyLib");
+    assert!(installed.join("lib.fin").exists());
+    assert!(installed.join(".finnignore").exists());
+    for ignored in ["notes.txt", ".lib.fin.swp", "out", ".finn"] {
+        assert!(!installed.join(ignored).exists(), "{} was installed", ignored);
+    }
+
+    // More build output in the source does not change the checksum
+    fs::write(lib.join("out/main.o"), "more build").unwrap();
+    fs::write(lib.join("notes.txt"), "done").unwrap();
+    finn(&home, &app_path).arg("--force").arg("add").arg("../TidyLib").assert().success();
+    assert_eq!(locked_checksum(&app_path), checksum);
+
+    // Nor does a swap file an editor leaves in the installed copy
+    fs::write(installed.join(".lib.fin.swp"), "swap").unwrap();
+    finn(&home, &app_path).arg("verify").assert().success();
+    finn(&home, &app_path).arg("sync").assert().success();
+
+    finn(&home, &app_path).arg("vendor").assert().success();
+    assert!(app_path.join("vendor/TidyLib/lib.fin").exists());
+    assert!(!app_path.join("vendor/TidyLib/.lib.fin.swp").exists());
+}
+
+#[test]
+fn test_include_list_selects_package_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    create_pkg(root, "SlimLib", "");
+
+    let lib = root.join("SlimLib");
+    let config = fs::read_to_string(lib.join("finn.toml")).unwrap()
+        + "include = [\"lib.fin\", \"src/\"]\n";
+    fs::write(lib.join("finn.toml"), config).unwrap();
+    fs::create_dir_all(lib.join("src/util")).unwrap();
+    fs::write(lib.join("src/util/strings.fin"), "").unwrap();
+    fs::create_dir_all(lib.join("docs")).unwrap();
+    fs::write(lib.join("docs/guide.md"), "").unwrap();
+    fs::write(lib.join("README.md"), "").unwrap();
+
+    let app_path = root.join("App");
+    finn(&home, root).arg("init").arg(app_path.to_str().unwrap()).arg("--yes").assert().success();
+    finn(&home, &app_path).arg("add").arg("../SlimLib").assert().success();
+
+    let installed = app_path.join(".finn/packages/SlimLib");
+    assert!(installed.join("finn.toml").exists());
+    assert!(installed.join("lib.fin").exists());
+    assert!(installed.join("src/util/strings.fin").exists());
+    assert!(!installed.join("README.md").exists());
+    assert!(!installed.join("docs").exists());
+}
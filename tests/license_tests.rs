@@ -0,0 +1,98 @@
+use predicates::prelude::*;
+use tempfile::TempDir;
+use std::fs;
+use std::path::Path;
+
+mod common;
+use common::{create_pkg, finn, init_project};
+
+const APACHE_HEADER: &str = "
+                                 Apache License
+                           Version 2.0, January 2004
+                        http://www.apache.org/licenses/
+";
+
+fn create_lib(root: &Path, name: &str, license: Option<&str>, license_text: Option<&str>) {
+    let extra = license.map(|l| format!("license = \"{}\"\n", l)).unwrap_or_default();
+    let lib_path = create_pkg(root, name, &extra);
+    if let Some(text) = license_text {
+        fs::write(lib_path.join("LICENSE"), text).unwrap();
+    }
+}
+
+fn setup_app(root: &Path) -> std::path::PathBuf {
+    create_lib(root, "MitLib", Some("MIT"), Some("MIT License\n\nCopyright (c) Mit Authors"));
+    create_lib(root, "ApacheLib", None, Some(APACHE_HEADER));
+    create_lib(root, "MysteryLib", None, None);
+
+    let home = root.join("home");
+    let app_path = root.join("App");
+    init_project(&home, &app_path);
+
+    for lib in ["MitLib", "ApacheLib", "MysteryLib"] {
+        finn(&home, &app_path)
+            .arg("add").arg(format!("../{}", lib))
+            .assert().success();
+    }
+    app_path
+}
+
+#[test]
+fn test_licenses_groups_and_notice() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let app = setup_app(temp.path());
+
+    finn(&home, &app)
+        .arg("licenses").arg("--notice").arg("NOTICE")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Apache-2.0 (1)"))
+        .stdout(predicate::str::contains("MIT (1)"))
+        .stdout(predicate::str::contains("UNKNOWN (1)"))
+        .stdout(predicate::str::contains("No recognizable license for: MysteryLib"));
+
+    let notice = fs::read_to_string(app.join("NOTICE")).unwrap();
+    assert!(notice.contains("MitLib 0.1.0"));
+    assert!(notice.contains("Copyright (c) Mit Authors"));
+    assert!(notice.contains("License: Apache-2.0"));
+}
+
+#[test]
+fn test_licenses_json() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let app = setup_app(temp.path());
+
+    let output = finn(&home, &app)
+        .arg("licenses").arg("--json")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["groups"]["MIT"][0], "MitLib");
+    assert_eq!(report["groups"]["UNKNOWN"][0], "MysteryLib");
+
+    let apache = report["packages"].as_array().unwrap().iter()
+        .find(|p| p["name"] == "ApacheLib").unwrap();
+    assert_eq!(apache["origin"], "detected");
+    assert_eq!(apache["license_files"][0], "LICENSE");
+}
+
+#[test]
+fn test_licenses_deny_list_fails() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let app = setup_app(temp.path());
+
+    let mut config = fs::read_to_string(app.join("finn.toml")).unwrap();
+    config.push_str("\n[licenses]\ndeny = [\"Apache-2.0\"]\n");
+    fs::write(app.join("finn.toml"), config).unwrap();
+
+    finn(&home, &app)
+        .arg("licenses")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Denied licenses found: 'ApacheLib' (Apache-2.0)"));
+}
@@ -0,0 +1,179 @@
+use predicates::prelude::*;
+use tempfile::TempDir;
+use std::fs;
+
+mod common;
+use common::{create_pkg, finn, init_app};
+
+#[test]
+fn test_dependency_manifest_cannot_escape_packages_dir() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    let app = init_app(&home, root);
+    create_pkg(root, "Payload", "");
+
+    for evil in ["../../../pwned", ".finn", "..", "con", "a/b"] {
+        let lib = create_pkg(root, "Evil", &format!("\n[packages]\n\"{}\" = \"../Payload\"\n", evil));
+        finn(&home, &app)
+            .arg("add").arg("../Evil")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Package validation failed"))
+            .stderr(predicate::str::contains("Invalid package name"));
+        finn(&home, &app)
+            .arg("--ignore-regulations").arg("add").arg("../Evil")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Package 'Evil' declares a dependency with an invalid name"))
+            .stderr(predicate::str::contains("Invalid package name"));
+        fs::remove_dir_all(lib).unwrap();
+    }
+
+    assert!(!root.join("pwned").exists());
+    assert!(!app.join(".finn/packages/Payload").exists());
+    assert!(!app.join("finn.lock").exists() || !fs::read_to_string(app.join("finn.lock")).unwrap().contains("Evil"));
+}
+
+#[test]
+fn test_lockfile_names_are_validated() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    let app = init_app(&home, root);
+    fs::create_dir_all(root.join("victim")).unwrap();
+    fs::write(root.join("victim/data.txt"), "keep me").unwrap();
+
+    fs::write(app.join("finn.lock"), r#"
+[packages."../../victim"]
+version = "HEAD"
+source = "../Payload"
+commit = "unknown"
+checksum = ""
+"#).unwrap();
+
+    for args in [vec!["sync"], vec!["--force", "sync"], vec!["vendor"], vec!["verify"]] {
+        finn(&home, &app)
+            .args(&args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("finn.lock contains an invalid package name"));
+    }
+    assert_eq!(fs::read_to_string(root.join("victim/data.txt")).unwrap(), "keep me");
+}
+
+#[test]
+fn test_remove_rejects_invalid_names() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    let app = init_app(&home, root);
+    fs::create_dir_all(app.join(".finn/packages")).unwrap();
+    fs::write(app.join(".finn/keep.txt"), "").unwrap();
+
+    // Listed in finn.toml, so only the name check stands between it and .finn being deleted
+    let config = fs::read_to_string(app.join("finn.toml")).unwrap()
+        .replace("[packages]\n", "[packages]\n\"..\" = \"../Payload\"\n");
+    fs::write(app.join("finn.toml"), config).unwrap();
+
+    finn(&home, &app)
+        .arg("remove").arg("..")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid package name '..'"));
+    assert!(app.join(".finn/keep.txt").exists());
+    assert!(app.join(".finn/packages").exists());
+}
+
+#[test]
+fn test_invalid_names_from_sources_are_rejected() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    let app = init_app(&home, root);
+    create_pkg(root, "con", "");
+    create_pkg(root, &"x".repeat(65), "");
+    create_pkg(root, "Fine-Name_1.2", "");
+
+    finn(&home, &app)
+        .arg("add").arg("../con")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("reserved device name"));
+    finn(&home, &app)
+        .arg("add").arg(format!("../{}", "x".repeat(65)))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("longer than 64 characters"));
+    finn(&home, &app).arg("add").arg("../Fine-Name_1.2").assert().success();
+}
+
+#[test]
+fn test_install_rejects_invalid_names() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    let mut server = mockito::Server::new();
+    let _m = server.mock("GET", "/api/packages/tool")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{ "name": "../../escaped", "repo_url": "https://example.com/tool.git" }"#)
+        .create();
+
+    // Names from registry metadata and from the last segment of a URL are both checked
+    finn(&home, root)
+        .env("FINN_REGISTRY_URL", server.url())
+        .arg("install").arg("tool")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid package name '../../escaped'"));
+    finn(&home, root)
+        .arg("--ignore-regulations").arg("install").arg("https://example.com/tools/..")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid package name '..'"));
+
+    assert!(!home.join(".finn/escaped").exists());
+    assert!(!home.join(".finn/bin").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinks_escaping_the_package_are_rejected() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let home = root.join("home");
+    let app = init_app(&home, root);
+    fs::write(root.join("secret.txt"), "secret").unwrap();
+
+    for (name, target) in [
+        ("Relative", "../../secret.txt"),
+        ("Absolute", "/etc/passwd"),
+        ("Indirect", "docs/../../secret.txt"),
+    ] {
+        let lib = create_pkg(root, name, "");
+        fs::create_dir_all(lib.join("docs")).unwrap();
+        std::os::unix::fs::symlink(target, lib.join("docs/link")).unwrap();
+
+        finn(&home, &app)
+            .arg("add").arg(format!("../{}", name))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("points outside the package"));
+        assert!(!app.join(".finn/packages").join(name).exists());
+    }
+
+    // Links that stay inside the package are fine
+    let lib = create_pkg(root, "Inside", "");
+    fs::create_dir_all(lib.join("docs")).unwrap();
+    std::os::unix::fs::symlink("../lib.fin", lib.join("docs/lib.fin")).unwrap();
+    finn(&home, &app).arg("add").arg("../Inside").assert().success();
+    assert!(fs::symlink_metadata(app.join(".finn/packages/Inside/docs/lib.fin")).unwrap().file_type().is_symlink());
+
+    // Nothing half-copied is left in the store
+    let leftovers: Vec<_> = fs::read_dir(home.join(".finn/store")).unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with(".tmp-"))
+        .collect();
+    assert!(leftovers.is_empty());
+}
@@ -0,0 +1,179 @@
+use predicates::prelude::*;
+use tempfile::TempDir;
+use std::fs;
+use std::path::Path;
+
+mod common;
+use common::{create_pkg, finn, init_project};
+
+fn create_lib(root: &Path, name: &str, license: Option<&str>, dep: Option<(&str, &str)>) {
+    let mut extra = license.map(|l| format!("license = \"{}\"\n", l)).unwrap_or_default();
+    extra.push_str("\n[packages]\n");
+    if let Some((dep_name, dep_path)) = dep {
+        extra.push_str(&format!("{} = \"{}\"\n", dep_name, dep_path));
+    }
+    create_pkg(root, name, &extra);
+}
+
+fn init_app(root: &Path, policy: &str) -> std::path::PathBuf {
+    let app_path = root.join("App");
+    init_project(&root.join("home"), &app_path);
+    fs::write(app_path.join("finn-policy.toml"), policy).unwrap();
+    app_path
+}
+
+#[test]
+fn test_policy_denied_package() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    create_lib(temp.path(), "BadLib", Some("MIT"), None);
+    let app = init_app(temp.path(), "denied-packages = [\"BadLib\"]\n");
+
+    finn(&home, &app)
+        .arg("add").arg("../BadLib")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Policy violation"))
+        .stderr(predicate::str::contains("package 'BadLib' is listed in denied-packages"));
+
+    assert!(!app.join(".finn/packages/BadLib").exists());
+    assert!(!fs::read_to_string(app.join("finn.toml")).unwrap().contains("BadLib"));
+}
+
+#[test]
+fn test_policy_allowed_sources() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    create_lib(temp.path(), "LocalLib", Some("MIT"), None);
+    let app = init_app(temp.path(), "allowed-sources = [\"github.com\"]\n");
+
+    finn(&home, &app)
+        .arg("add").arg("../LocalLib")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("(host 'local'), which is not in allowed-sources [github.com]"));
+
+    // Allowing local sources lets the same package through
+    fs::write(app.join("finn-policy.toml"), "allowed-sources = [\"github.com\", \"local\"]\n").unwrap();
+    finn(&home, &app)
+        .arg("add").arg("../LocalLib")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_policy_max_depth_and_license() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    create_lib(temp.path(), "LibB", Some("GPL-3.0-only"), None);
+    create_lib(temp.path(), "LibA", Some("MIT"), Some(("LibB", "../LibB")));
+    let app = init_app(temp.path(), "max-depth = 1\n");
+
+    finn(&home, &app)
+        .arg("add").arg("../LibA")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("package 'LibB' is at dependency depth 2, but max-depth is 1"));
+
+    fs::write(app.join("finn-policy.toml"), "allowed-licenses = [\"MIT\", \"Apache-2.0\"]\n").unwrap();
+    finn(&home, &app)
+        .arg("add").arg("../LibA")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("package 'LibB' is licensed under 'GPL-3.0-only'"));
+}
+
+#[test]
+fn test_policy_rejects_c_packages() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let lib_path = temp.path().join("NativeLib");
+    fs::create_dir(&lib_path).unwrap();
+    fs::write(lib_path.join("Makefile"), "all:\n\techo build\n").unwrap();
+
+    let app = init_app(temp.path(), "allow-c-packages = false\n");
+
+    finn(&home, &app)
+        .arg("add").arg("../NativeLib")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("package 'NativeLib' is a C/C++ package and allow-c-packages is false"));
+}
+
+#[test]
+fn test_policy_require_checksum_on_sync() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    create_lib(temp.path(), "SyncLib", Some("MIT"), None);
+    let app = init_app(temp.path(), "require-checksum = true\n");
+
+    let lib_str = temp.path().join("SyncLib").to_str().unwrap().replace("\\", "/");
+    fs::write(app.join("finn.toml"), format!(r#"
+[project]
+name = "App"
+version = "0.1.0"
+envpath = ".finn"
+entrypoint = "main.fin"
+
+[packages]
+SyncLib = "{}"
+"#, lib_str)).unwrap();
+
+    finn(&home, &app)
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("package 'SyncLib' has no checksum in finn.lock"));
+}
+
+#[test]
+fn test_policy_require_checksum_covers_transitive_packages() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    create_lib(temp.path(), "LibB", Some("MIT"), None);
+    create_lib(temp.path(), "LibA", Some("MIT"), Some(("LibB", "../LibB")));
+    let app = init_app(temp.path(), "");
+
+    finn(&home, &app)
+        .arg("add").arg("../LibA")
+        .assert()
+        .success();
+
+    // Only the transitive package lost its checksum
+    let lock = fs::read_to_string(app.join("finn.lock")).unwrap();
+    let (before, after) = lock.split_once("[packages.LibB]").unwrap();
+    let start = after.find("checksum = \"").unwrap() + 12;
+    let end = start + after[start..].find('"').unwrap();
+    fs::write(app.join("finn.lock"), format!("{}[packages.LibB]{}{}", before, &after[..start], &after[end..])).unwrap();
+    fs::write(app.join("finn-policy.toml"), "require-checksum = true\n").unwrap();
+
+    finn(&home, &app)
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("package 'LibB' has no checksum in finn.lock"));
+}
+
+#[test]
+fn test_policy_applies_to_install() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let native = temp.path().join("NativeLib");
+    fs::create_dir(&native).unwrap();
+    fs::write(native.join("Makefile"), "all:\n\techo build\n").unwrap();
+    create_lib(temp.path(), "GplTool", Some("GPL-3.0-only"), None);
+    let app = init_app(temp.path(), "allow-c-packages = false\nallowed-licenses = [\"MIT\"]\n");
+
+    // --ignore-regulations skips validation, but not the policy
+    finn(&home, &app)
+        .arg("--ignore-regulations").arg("install").arg("../NativeLib")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("package 'NativeLib' is a C/C++ package and allow-c-packages is false"));
+
+    finn(&home, &app)
+        .arg("--ignore-regulations").arg("install").arg("../GplTool")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("package 'GplTool' is licensed under 'GPL-3.0-only'"));
+}
@@ -1,8 +1,10 @@
-use assert_cmd::Command;
 use predicates::prelude::*;
 use tempfile::TempDir;
 use mockito::Server;
 
+mod common;
+use common::{finn, init_project};
+
 #[test]
 fn test_add_from_registry_mock() {
     // 1. Start Mock Server
@@ -22,16 +24,14 @@ fn test_add_from_registry_mock() {
 
     // 3. Setup Project
     let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
     let project_path = temp.path();
 
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .arg("init").arg(project_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(temp_home.path(), project_path);
 
     // 4. Run 'finn add' pointing to Mock Server
     // We use the env var override we implemented in RegistryClient
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(project_path)
+    finn(temp_home.path(), project_path)
         .env("FINN_REGISTRY_URL", &url) // Point to localhost mock
         .arg("add")
         .arg("mock-pkg")
@@ -52,13 +52,11 @@ fn test_registry_404() {
         .create();
 
     let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
     
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .arg("init").arg(temp.path().to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(temp_home.path(), temp.path());
 
-    Command::new(assert_cmd::cargo::cargo_bin("finn"))
-        .current_dir(temp.path())
+    finn(temp_home.path(), temp.path())
         .env("FINN_REGISTRY_URL", &url)
         .arg("add")
         .arg("unknown-pkg")
@@ -66,3 +64,105 @@ fn test_registry_404() {
         .failure()
         .stderr(predicate::str::contains("not found in registry"));
 }
+
+#[test]
+fn test_error_causes_are_printed() {
+    let mut server = Server::new();
+    let url = server.url();
+
+    let _m = server.mock("GET", "/api/packages/unknown-pkg")
+        .with_status(404)
+        .create();
+
+    let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
+
+    init_project(temp_home.path(), temp.path());
+
+    // Without --verbose the whole chain is printed on one line
+    finn(temp_home.path(), temp.path())
+        .env("FINN_REGISTRY_URL", &url)
+        .arg("add")
+        .arg("unknown-pkg")
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(r"\[ERROR\] Failed to resolve package 'unknown-pkg': .*not found in registry").unwrap())
+        .stderr(predicate::str::contains("Caused by").not());
+
+    // With --verbose each cause gets its own line
+    finn(temp_home.path(), temp.path())
+        .env("FINN_REGISTRY_URL", &url)
+        .arg("--verbose").arg("add")
+        .arg("unknown-pkg")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("[ERROR] Failed to resolve package 'unknown-pkg'\n"))
+        .stderr(predicate::str::contains("  Caused by: ").and(predicate::str::contains("not found in registry")));
+}
+
+fn mock_popular(server: &mut mockito::ServerGuard) -> mockito::Mock {
+    server.mock("GET", "/api/packages/popular")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"[
+            { "name": "fin-http", "downloads": 250000 },
+            { "name": "fin-json", "downloads": 120000 }
+        ]"#)
+        .create()
+}
+
+#[test]
+fn test_add_rejects_confusable_name() {
+    let mut server = Server::new();
+    let url = server.url();
+    let _popular = mock_popular(&mut server);
+
+    let _m = server.mock("GET", "/api/packages/fin_http")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{ "name": "fin_http", "repo_url": "https://github.com/evil/fin_http.git" }"#)
+        .create();
+
+    let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
+    init_project(temp_home.path(), temp.path());
+
+    // Non-interactive: must fail instead of prompting
+    finn(temp_home.path(), temp.path())
+        .env("FINN_REGISTRY_URL", &url)
+        .arg("add")
+        .arg("fin_http")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("looks like a confusable of the more popular 'fin-http'"))
+        .stderr(predicate::str::contains("--allow-confusable"));
+
+    let config = std::fs::read_to_string(temp.path().join("finn.toml")).unwrap();
+    assert!(!config.contains("fin_http"), "Confusable package must not be recorded in finn.toml");
+}
+
+#[test]
+fn test_add_popular_name_is_not_flagged() {
+    let mut server = Server::new();
+    let url = server.url();
+    let _popular = mock_popular(&mut server);
+
+    let _m = server.mock("GET", "/api/packages/fin-http")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{ "name": "fin-http", "repo_url": "https://github.com/test/fin-http.git" }"#)
+        .create();
+
+    let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
+    init_project(temp_home.path(), temp.path());
+
+    // Fails later at git clone, but never on the similarity check
+    finn(temp_home.path(), temp.path())
+        .env("FINN_REGISTRY_URL", &url)
+        .arg("add")
+        .arg("fin-http")
+        .assert()
+        .stdout(predicate::str::contains("Resolving 'fin-http'"))
+        .stderr(predicate::str::contains("confusable").not());
+}
@@ -0,0 +1,89 @@
+use tempfile::TempDir;
+use std::path::Path;
+
+mod common;
+use common::{create_pkg, finn, init_project};
+
+fn create_lib(root: &Path, name: &str, dep: Option<(&str, &str)>) {
+    let mut extra = String::from("license = \"MIT\"\n\n[packages]\n");
+    if let Some((dep_name, dep_path)) = dep {
+        extra.push_str(&format!("{} = \"{}\"\n", dep_name, dep_path));
+    }
+    create_pkg(root, name, &extra);
+}
+
+fn setup_app(root: &Path) -> std::path::PathBuf {
+    create_lib(root, "LibB", None);
+    create_lib(root, "LibA", Some(("LibB", "../LibB")));
+
+    let home = root.join("home");
+    let app_path = root.join("App");
+    init_project(&home, &app_path);
+    finn(&home, &app_path)
+        .arg("add").arg("../LibA")
+        .assert().success();
+    app_path
+}
+
+fn sbom(app: &Path, format: &str) -> String {
+    let home = app.parent().unwrap().join("home");
+    let output = finn(&home, app)
+        .arg("sbom").arg("--format").arg(format)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "sbom failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_sbom_cyclonedx() {
+    let temp = TempDir::new().unwrap();
+    let app = setup_app(temp.path());
+
+    let first = sbom(&app, "cyclonedx-json");
+    assert_eq!(first, sbom(&app, "cyclonedx-json"), "SBOM output must be deterministic");
+
+    let doc: serde_json::Value = serde_json::from_str(&first).unwrap();
+    assert_eq!(doc["bomFormat"], "CycloneDX");
+    assert_eq!(doc["metadata"]["component"]["name"], "App");
+
+    let components = doc["components"].as_array().unwrap();
+    let names: Vec<&str> = components.iter().map(|c| c["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["LibA", "LibB"]);
+    assert_eq!(components[0]["licenses"][0]["expression"], "MIT");
+    assert_eq!(components[0]["hashes"][0]["alg"], "SHA-256");
+
+    let lib_a = doc["dependencies"].as_array().unwrap().iter()
+        .find(|d| d["ref"] == "LibA@HEAD").unwrap();
+    assert_eq!(lib_a["dependsOn"][0], "LibB@HEAD");
+}
+
+#[test]
+fn test_sbom_spdx() {
+    let temp = TempDir::new().unwrap();
+    let app = setup_app(temp.path());
+
+    let first = sbom(&app, "spdx-json");
+    assert_eq!(first, sbom(&app, "spdx-json"), "SBOM output must be deterministic");
+
+    let doc: serde_json::Value = serde_json::from_str(&first).unwrap();
+    assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+    assert_eq!(doc["packages"].as_array().unwrap().len(), 3);
+
+    let relationships = doc["relationships"].as_array().unwrap();
+    assert!(relationships.iter().any(|r| r["spdxElementId"] == "SPDXRef-Package-LibA"
+        && r["relationshipType"] == "DEPENDS_ON"
+        && r["relatedSpdxElement"] == "SPDXRef-Package-LibB"));
+}
+
+#[test]
+fn test_sbom_rejects_unknown_format() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path().join("home");
+    let app = setup_app(temp.path());
+
+    finn(&home, &app)
+        .arg("sbom").arg("--format").arg("xml")
+        .assert()
+        .failure();
+}
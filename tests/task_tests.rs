@@ -1,16 +1,17 @@
-use assert_cmd::Command;
 use predicates::prelude::*;
 use tempfile::TempDir;
 use std::fs;
 
+mod common;
+use common::{finn, init_project};
+
 #[test]
 fn test_task_runner_basic() {
     let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
     let project_path = temp.path();
 
-    Command::cargo_bin("finn").unwrap()
-        .arg("init").arg(project_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(temp_home.path(), project_path);
 
     let config_path = project_path.join("finn.toml");
     
@@ -31,8 +32,7 @@ greet = "{} Hello"
 
     fs::write(&config_path, new_config).unwrap();
 
-    Command::cargo_bin("finn").unwrap()
-        .current_dir(project_path)
+    finn(temp_home.path(), project_path)
         .arg("do")
         .arg("greet")
         .assert()
@@ -43,11 +43,10 @@ greet = "{} Hello"
 #[test]
 fn test_task_runner_with_args() {
     let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
     let project_path = temp.path();
 
-    Command::cargo_bin("finn").unwrap()
-        .arg("init").arg(project_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(temp_home.path(), project_path);
 
     let config_path = project_path.join("finn.toml");
     let script_cmd = if cfg!(windows) { "cmd /c echo" } else { "echo" };
@@ -66,8 +65,7 @@ echo_args = "{}"
     fs::write(&config_path, new_config).unwrap();
 
     // FIX: Add "--" before extra arguments
-    Command::cargo_bin("finn").unwrap()
-        .current_dir(project_path)
+    finn(temp_home.path(), project_path)
         .arg("do")
         .arg("echo_args")
         .arg("--") // Required because of #[arg(last = true)]
@@ -80,14 +78,12 @@ echo_args = "{}"
 #[test]
 fn test_task_runner_missing_script() {
     let temp = TempDir::new().unwrap();
+    let temp_home = TempDir::new().unwrap();
     let project_path = temp.path();
 
-    Command::cargo_bin("finn").unwrap()
-        .arg("init").arg(project_path.to_str().unwrap()).arg("--yes")
-        .assert().success();
+    init_project(temp_home.path(), project_path);
 
-    Command::cargo_bin("finn").unwrap()
-        .current_dir(project_path)
+    finn(temp_home.path(), project_path)
         .arg("do")
         .arg("missing_task")
         .assert()
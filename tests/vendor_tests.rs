@@ -0,0 +1,84 @@
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+mod common;
+use common::{create_pkg, finn, init_app};
+
+#[test]
+fn test_vendor_and_sync_from_vendor_dir() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let sources = temp.path().join("sources");
+    fs::create_dir(&sources).unwrap();
+    let inner = create_pkg(&sources, "inner", "");
+    let outer = create_pkg(&sources, "outer", &format!("\n[packages]\ninner = {:?}\n", inner.to_str().unwrap()));
+
+    let app = init_app(temp_home.path(), temp.path());
+    finn(temp_home.path(), &app).arg("add").arg(outer.to_str().unwrap()).assert().success();
+
+    finn(temp_home.path(), &app)
+        .arg("vendor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Vendored 2 packages"))
+        .stdout(predicate::str::contains("[vendor]\ndirectory = \"vendor\""));
+
+    // Transitive packages are vendored too, each with its checksum file
+    for name in ["outer", "inner"] {
+        let checksum: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(app.join("vendor").join(name).join(".finn-checksum.json")).unwrap()
+        ).unwrap();
+        assert!(checksum["files"]["lib.fin"].is_string());
+    }
+
+    // Without the original sources or any cache, sync installs from the vendor directory
+    let config = fs::read_to_string(app.join("finn.toml")).unwrap();
+    fs::write(app.join("finn.toml"), format!("{}\n[vendor]\ndirectory = \"vendor\"\n", config)).unwrap();
+    fs::remove_dir_all(&sources).unwrap();
+    fs::remove_dir_all(app.join(".finn/packages")).unwrap();
+    let fresh_home = TempDir::new().unwrap();
+
+    finn(fresh_home.path(), &app)
+        .arg("--offline").arg("sync")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Installed inner (vendored)"));
+    assert_eq!(fs::read_to_string(app.join(".finn/packages/inner/lib.fin")).unwrap(), "pub fun test() {}");
+    assert!(!app.join(".finn/packages/inner/.finn-checksum.json").exists());
+
+    // Edited vendored sources are rejected
+    fs::write(app.join("vendor/inner/lib.fin"), "pub fun evil() {}").unwrap();
+    fs::remove_dir_all(app.join(".finn/packages")).unwrap();
+    finn(fresh_home.path(), &app)
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'lib.fin' was changed"));
+}
+
+#[test]
+fn test_vendor_removes_stale_packages() {
+    let temp_home = TempDir::new().unwrap();
+    let temp = TempDir::new().unwrap();
+    let kept = create_pkg(temp.path(), "kept", "");
+    let dropped = create_pkg(temp.path(), "dropped", "");
+
+    let app = init_app(temp_home.path(), temp.path());
+    finn(temp_home.path(), &app).arg("add").arg(kept.to_str().unwrap()).assert().success();
+    finn(temp_home.path(), &app).arg("add").arg(dropped.to_str().unwrap()).assert().success();
+    finn(temp_home.path(), &app).arg("vendor").arg("third_party").assert().success();
+    assert!(app.join("third_party/dropped").exists());
+
+    fs::create_dir_all(app.join("third_party/README-notes")).unwrap();
+    finn(temp_home.path(), &app).arg("remove").arg("dropped").assert().success();
+    finn(temp_home.path(), &app)
+        .arg("vendor").arg("third_party")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("directory = \"third_party\""));
+
+    assert!(app.join("third_party/kept/.finn-checksum.json").exists());
+    assert!(!app.join("third_party/dropped").exists());
+    assert!(app.join("third_party/README-notes").exists());
+}